@@ -15,19 +15,28 @@
 
 
 use actix_files::Files;
-use actix_multipart::Multipart;
-use actix_web::{get, post, App, Error as ActixError, HttpResponse, HttpServer, Responder};
+use actix_multipart::{Field, Multipart};
+use actix_web::{
+    delete, get, http::header, post, put, web, App, Error as ActixError, HttpRequest,
+    HttpResponse, HttpServer, Responder,
+};
+use anyhow::Context;
 use futures_util::StreamExt;
 use once_cell::sync::Lazy;
 use sanitize_filename::sanitize;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
+    collections::{BTreeMap, HashMap},
     env,
     ffi::OsStr,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
     process::Stdio,
-    time::SystemTime,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock, Weak,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tokio::{fs, fs::File, io::AsyncWriteExt, process::Command};
 use uuid::Uuid;
@@ -40,7 +49,8 @@ const OUT_HEIGHT: i32 = 1280;
 const CRF: i32 = 23;
 const PRESET: &str = "veryfast";
 const ALLOW_TRIM: bool = true;
-const FILL_MODE: &str = "crop"; // "crop" | "pad"
+const FILL_MODE: FillMode = FillMode::Crop; // crop | pad | blur
+const OUT_FPS: u32 = 30;
 
 // ================== BINARY RESOLUTION ==================
 #[derive(Clone)]
@@ -74,344 +84,3154 @@ static BINS: Lazy<Bins> = Lazy::new(|| {
 });
 
 // ================== TEMPLATE MODEL ==================
-#[derive(Clone)]
-#[allow(dead_code)]
+// Template dibaca dari manifest `templates/<key>/template.toml` (atau `template.json`).
+// Path overlay di manifest relatif terhadap folder template.
+const TEMPLATES_DIR: &str = "templates";
+const MANIFEST_NAMES: [&str; 2] = ["template.toml", "template.json"];
+const TEMPLATE_POLL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
 enum OverlayType {
     Full,
     Band { h: Option<i32>, x: Option<String>, y: Option<String> },
     Logo { w: Option<i32>, h: Option<i32>, x: Option<String>, y: Option<String> },
+    Text(TextBox),
 }
 
-#[derive(Clone)]
-struct Overlay {
-    path: &'static str,
-    typ: OverlayType,
-    start: f64,
-    end: f64,
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum TextAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
 }
 
-#[derive(Clone)]
-struct Template {
-    key: &'static str,
-    title: &'static str,
-    overlays: Vec<Overlay>,
+// Teks per peserta (drawtext). Isinya diambil dari field form `f_<field>`.
+#[derive(Clone, Deserialize, Serialize)]
+struct TextBox {
+    field: String,
+    label: Option<String>,
+    #[serde(default)]
+    required: bool,
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+    // nama font di tabel [fonts] template
+    font: String,
+    // ukuran maksimum; diperkecil otomatis sampai muat di kotak (tidak kurang dari min_size)
+    #[serde(default = "default_font_size")]
+    size: i32,
+    min_size: Option<i32>,
+    #[serde(default = "default_max_lines")]
+    max_lines: usize,
+    #[serde(default = "default_text_color")]
+    color: String,
+    stroke: Option<String>,
+    #[serde(default = "default_stroke_width")]
+    stroke_width: i32,
+    #[serde(default)]
+    align: TextAlign,
 }
 
-static TEMPLATES: Lazy<Vec<Template>> = Lazy::new(|| {
-    vec![Template {
-        key: "reuni_391",
-        title: "Reuni SMA 3 Jakarta • 24 Agustus 2025 (3-91)",
-        overlays: vec![Overlay {
-            path: "templates/2d.png",
-            typ: OverlayType::Full,
-            start: 0.0,
-            end: 30.0,
-        }],
-    }]
-});
+fn default_font_size() -> i32 {
+    48
+}
 
-// ================== HELPERS ==================
-fn ensure_dirs() -> std::io::Result<()> {
-    for d in ["uploads", "outputs", "templates"] {
-        std::fs::create_dir_all(d)?;
-    }
-    Ok(())
+fn default_max_lines() -> usize {
+    1
 }
 
-fn random_name(prefix: &str) -> String {
-    format!("{}{}", prefix, Uuid::new_v4().simple())
+fn default_text_color() -> String {
+    "white".into()
 }
 
-async fn ffprobe_duration(ffprobe: &str, path: &Path) -> anyhow::Result<f64> {
-    let out = Command::new(ffprobe)
-        .arg("-v")
-        .arg("error")
-        .arg("-show_entries")
-        .arg("format=duration")
-        .arg("-of")
-        .arg("default=noprint_wrappers=1:nokey=1")
-        .arg(path.as_os_str())
-        .stdout(Stdio::piped())
-        .output()
-        .await?;
+fn default_stroke_width() -> i32 {
+    2
+}
 
-    if !out.status.success() {
-        anyhow::bail!("ffprobe failed: {}", String::from_utf8_lossy(&out.stderr));
+impl TextBox {
+    fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.field)
     }
-    let s = String::from_utf8_lossy(&out.stdout).trim().to_string();
-    let dur = s.parse::<f64>().unwrap_or(0.0);
-    Ok(dur.max(0.0))
-}
 
-fn html_escape(s: &str) -> String {
-    htmlescape::encode_minimal(s)
+    fn min_size(&self) -> i32 {
+        self.min_size.unwrap_or(self.size / 2).clamp(8, self.size)
+    }
 }
 
-#[derive(Default)]
-struct PostFields {
-    source: Option<String>,
-    template: Option<String>,
-    title: Option<String>,
-    upload_path: Option<PathBuf>,
-    upload_ext: Option<String>,
-    upload_mime: Option<String>,
+// Warna drawtext: nama, 0xRRGGBB / #RRGGBB, opsional @alpha.
+fn valid_color(c: &str) -> bool {
+    !c.is_empty()
+        && c
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '#' | '@' | '.'))
 }
 
-async fn save_multipart(mut payload: Multipart) -> Result<PostFields, ActixError> {
-    let mut fields = PostFields::default();
+// Cara overlay diputar. `still` = PNG biasa (di-loop sebagai satu frame);
+// `loop`/`once` untuk GIF, APNG, dan WebM VP9 ber-alpha.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum OverlaySource {
+    Still,
+    Loop,
+    Once,
+}
 
-    while let Some(item) = payload.next().await {
-        let mut field = item?;
-        let cd = field.content_disposition().clone();
-        let name = cd.get_name().unwrap_or("").to_string();
+#[derive(Clone, Deserialize, Serialize)]
+struct Overlay {
+    // kosong untuk overlay teks
+    #[serde(default)]
+    path: PathBuf,
+    #[serde(flatten)]
+    typ: OverlayType,
+    // kosong = ditebak dari ekstensi (gif/apng/webm -> loop)
+    #[serde(default)]
+    source: Option<OverlaySource>,
+    #[serde(default = "default_overlay_start")]
+    start: TimeSpec,
+    #[serde(default = "default_overlay_end")]
+    end: TimeSpec,
+    // 0..1, dikalikan ke alpha overlay
+    #[serde(default = "default_opacity")]
+    opacity: f64,
+    // durasi fade (detik) di awal/akhir jendela start..end
+    #[serde(default)]
+    fade_in: f64,
+    #[serde(default)]
+    fade_out: f64,
+    // gerak posisi/skala; kosong = statis di x/y tipe overlay
+    #[serde(default)]
+    keyframes: Vec<Keyframe>,
+    #[serde(default)]
+    blend: BlendMode,
+    // ukuran asli hasil ffprobe saat validasi (untuk sisi -1 pada animasi scale)
+    #[serde(skip)]
+    probed: Option<(i32, i32)>,
+}
 
-        if name == "video" {
-            let filename = cd
-                .get_filename()
-                .map(|s| sanitize(s))
-                .unwrap_or_else(|| format!("upload-{}.bin", Uuid::new_v4()));
+// Mode blend overlay gambar. `normal` = overlay alpha biasa; lainnya lewat filter `blend`.
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    #[serde(alias = "soft_light")]
+    Softlight,
+}
 
-            let ext = Path::new(&filename)
-                .extension()
-                .and_then(OsStr::to_str)
-                .unwrap_or("")
-                .to_ascii_lowercase();
+impl BlendMode {
+    fn filter_name(self) -> &'static str {
+        match self {
+            BlendMode::Normal => "normal",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Screen => "screen",
+            BlendMode::Overlay => "overlay",
+            BlendMode::Softlight => "softlight",
+        }
+    }
 
-            let tmp_path = PathBuf::from(format!("uploads/{}", random_name("raw_")));
-            let mut f = File::create(&tmp_path).await?;
-            let mut size: u64 = 0;
+    // Input pertama `blend` adalah "top". Overlay ala editor foto memilih terang/gelap
+    // dari lapisan dasar, jadi video harus di posisi top; mode lain memakai lapisan overlay.
+    fn base_on_top(self) -> bool {
+        self == BlendMode::Overlay
+    }
+}
 
-            while let Some(chunk) = field.next().await {
-                let bytes = chunk?;
-                size += bytes.len() as u64;
-                f.write_all(&bytes).await?;
-            }
-            f.flush().await?;
+// Nilai keyframe: angka (piksel) atau ekspresi FFmpeg mentah seperti x/y band/logo.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+enum KfValue {
+    Num(f64),
+    Expr(String),
+}
 
-            if size < 1_000 {
-                return Err(actix_web::error::ErrorBadRequest(
-                    "Upload video kosong/tidak valid",
-                ));
-            }
+impl KfValue {
+    fn expr(&self) -> String {
+        match self {
+            KfValue::Num(n) if *n < 0.0 => format!("({n:.3})"),
+            KfValue::Num(n) => format!("{n:.3}"),
+            KfValue::Expr(e) => format!("({e})"),
+        }
+    }
+}
 
-            fields.upload_path = Some(tmp_path);
-            fields.upload_ext = Some(ext);
+// Kurva dari keyframe ini ke keyframe berikutnya.
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Ease {
+    #[default]
+    Linear,
+    In,
+    Out,
+    InOut,
+}
 
-            fields.upload_mime = Some(
-                field
-                    .content_type()
-                    .map(|mime| mime.essence_str().to_string())
-                    .unwrap_or_else(|| "application/octet-stream".to_string()),
-            );
-        } else {
-            // text fields
-            let mut bytes = Vec::new();
-            while let Some(chunk) = field.next().await {
-                let data = chunk?;
-                bytes.extend_from_slice(&data);
-            }
-            let text = String::from_utf8(bytes).unwrap_or_default();
-            match name.as_str() {
-                "source" => fields.source = Some(text),
-                "template" => fields.template = Some(text),
-                "title" => fields.title = Some(text),
-                _ => {}
-            }
+impl Ease {
+    // `p` = progres segmen 0..1 (sudah di-clip)
+    fn apply(self, p: &str) -> String {
+        match self {
+            Ease::Linear => p.to_string(),
+            Ease::In => format!("pow({p}\\,2)"),
+            Ease::Out => format!("(1-pow(1-{p}\\,2))"),
+            Ease::InOut => format!("({p}*{p}*(3-2*{p}))"),
         }
     }
+}
 
-    Ok(fields)
+// Titik gerak overlay. `t` relatif terhadap `start` overlay; properti yang tidak diisi
+// diinterpolasi di antara keyframe lain yang mengisinya.
+#[derive(Clone, Deserialize, Serialize)]
+struct Keyframe {
+    t: f64,
+    x: Option<KfValue>,
+    y: Option<KfValue>,
+    // faktor terhadap ukuran dasar overlay
+    scale: Option<f64>,
+    #[serde(default)]
+    ease: Ease,
 }
 
-// Build filter_complex graph
-fn build_filter_graph(tpl: &Template) -> (String, usize) {
-    let mut chains: Vec<String> = Vec::new();
+// Titik waktu overlay: detik absolut (angka), "end" / "end-3" relatif ke akhir video,
+// atau "25%" dari durasi. Diselesaikan per render setelah durasi video diketahui.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "TimeRaw", into = "TimeRaw")]
+enum TimeSpec {
+    Secs(f64),
+    FromEnd(f64),
+    Percent(f64),
+}
 
-    if FILL_MODE == "crop" {
-        let ratio = OUT_WIDTH as f64 / OUT_HEIGHT as f64;
-        chains.push(format!(
-            "[0:v]scale=if(gte(a\\,{ratio})\\,-2\\,{OUT_WIDTH}):if(gte(a\\,{ratio})\\,{OUT_HEIGHT}\\,-2):flags=fast_bilinear,\
-             crop={OUT_WIDTH}:{OUT_HEIGHT}:(iw-{OUT_WIDTH})/2:(ih-{OUT_HEIGHT})/2,setsar=1[base]"
-        ));
-    } else {
-        chains.push(format!(
-            "[0:v]scale={OUT_WIDTH}:{OUT_HEIGHT}:force_original_aspect_ratio=decrease:flags=fast_bilinear,\
-             pad={OUT_WIDTH}:{OUT_HEIGHT}:(ow-iw)/2:(oh-ih)/2,setsar=1[base]"
-        ));
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum TimeRaw {
+    Secs(f64),
+    Text(String),
+}
+
+impl TryFrom<TimeRaw> for TimeSpec {
+    type Error = String;
+
+    fn try_from(raw: TimeRaw) -> Result<Self, String> {
+        let (spec, s) = match raw {
+            TimeRaw::Secs(v) => (Some(TimeSpec::Secs(v)), v.to_string()),
+            TimeRaw::Text(s) => {
+                let t = s.trim();
+                let spec = if let Some(rest) = t.strip_prefix("end") {
+                    match rest.trim() {
+                        "" => Some(TimeSpec::FromEnd(0.0)),
+                        r => r
+                            .strip_prefix('-')
+                            .and_then(|n| n.trim().parse().ok())
+                            .map(TimeSpec::FromEnd),
+                    }
+                } else if let Some(p) = t.strip_suffix('%') {
+                    p.trim().parse().ok().map(TimeSpec::Percent)
+                } else {
+                    t.parse().ok().map(TimeSpec::Secs)
+                };
+                (spec, s)
+            }
+        };
+        match spec {
+            Some(t) if t.is_valid() => Ok(t),
+            _ => Err(format!(
+                "waktu `{s}` tidak valid (detik >= 0, \"end\", \"end-3\", atau \"25%\")"
+            )),
+        }
     }
+}
 
-    let mut prev = Cow::Borrowed("base");
-    let mut ov_index = 0usize;
-    let mut input_count = 1usize; // 0 adalah video utama
+impl From<TimeSpec> for TimeRaw {
+    fn from(t: TimeSpec) -> Self {
+        match t {
+            TimeSpec::Secs(v) => TimeRaw::Secs(v),
+            TimeSpec::FromEnd(0.0) => TimeRaw::Text("end".into()),
+            TimeSpec::FromEnd(n) => TimeRaw::Text(format!("end-{n}")),
+            TimeSpec::Percent(p) => TimeRaw::Text(format!("{p}%")),
+        }
+    }
+}
 
-    for ol in &tpl.overlays {
-        input_count += 1;
-        let in_tag = format!("{}", input_count - 1);
-        ov_index += 1;
-        let tag_ov = format!("ov{}", ov_index);
+impl TimeSpec {
+    fn is_valid(self) -> bool {
+        match self {
+            TimeSpec::Secs(v) | TimeSpec::FromEnd(v) => v >= 0.0 && v.is_finite(),
+            TimeSpec::Percent(p) => (0.0..=100.0).contains(&p),
+        }
+    }
 
-        match &ol.typ {
-            OverlayType::Full => {
-                chains.push(format!(
-                    "[{in_tag}:v]scale={OUT_WIDTH}:{OUT_HEIGHT}:flags=fast_bilinear[{tag_ov}]"
-                ));
-                let (start, end) = (format!("{:.3}", ol.start), format!("{:.3}", ol.end));
-                chains.push(format!(
-                    "[{prev}][{tag_ov}]overlay=shortest=1:x=0:y=0:enable=between(t\\,{start}\\,{end})[v{ov_index}]"
-                ));
-            }
-            OverlayType::Band { h, x, y } => {
-                let hh = h.unwrap_or(160);
-                chains.push(format!(
-                    "[{in_tag}:v]scale={OUT_WIDTH}:{hh}:flags=fast_bilinear[{tag_ov}]"
-                ));
-                let x = x.clone().unwrap_or_else(|| "(main_w-w)/2".into());
-                let y = y.clone().unwrap_or_else(|| "main_h-h".into());
-                let (start, end) = (format!("{:.3}", ol.start), format!("{:.3}", ol.end));
-                chains.push(format!(
-                    "[{prev}][{tag_ov}]overlay=shortest=1:x={x}:y={y}:enable=between(t\\,{start}\\,{end})[v{ov_index}]"
-                ));
-            }
-            OverlayType::Logo { w, h, x, y } => {
-                let ww = w.unwrap_or(220);
-                let hh = h.unwrap_or(-1);
-                chains.push(format!(
-                    "[{in_tag}:v]scale={ww}:{hh}:flags=fast_bilinear[{tag_ov}]"
-                ));
-                let x = x.clone().unwrap_or_else(|| "main_w-w-24".into());
-                let y = y.clone().unwrap_or_else(|| "24".into());
-                let (start, end) = (format!("{:.3}", ol.start), format!("{:.3}", ol.end));
-                chains.push(format!(
-                    "[{prev}][{tag_ov}]overlay=shortest=1:x={x}:y={y}:enable=between(t\\,{start}\\,{end})[v{ov_index}]"
-                ));
-            }
+    // detik absolut bila tidak bergantung pada durasi video
+    fn secs(self) -> Option<f64> {
+        match self {
+            TimeSpec::Secs(v) => Some(v),
+            _ => None,
         }
+    }
 
-        prev = Cow::Owned(format!("v{}", ov_index));
+    fn resolve(self, dur: f64) -> f64 {
+        match self {
+            TimeSpec::Secs(v) => v,
+            TimeSpec::FromEnd(n) => (dur - n).max(0.0),
+            TimeSpec::Percent(p) => dur * p / 100.0,
+        }
     }
+}
 
-    (chains.join(";"), ov_index)
+fn default_overlay_start() -> TimeSpec {
+    TimeSpec::Secs(0.0)
 }
 
-// ================== HTML ==================
-static HOME_JS: &str = r#"
-(function(){
-  const form = document.getElementById('twb-form');
-  const srcRadios = form.querySelectorAll('input[name="source"]');
-  const uploadPane = document.getElementById('upload-pane');
-  const recordPane = document.getElementById('record-pane');
+fn default_overlay_end() -> TimeSpec {
+    TimeSpec::Secs(MAX_DURATION_SEC)
+}
 
-  const cam = document.getElementById('cam');
-  const playback = document.getElementById('playback');
-  const btnOpen = document.getElementById('btnOpen');
-  const btnRec  = document.getElementById('btnRec');
-  const btnStop = document.getElementById('btnStop');
-  const timerEl = document.getElementById('timer');
+// Jendela tampil overlay untuk satu render: detik absolut, sudah dipotong ke durasi output.
+#[derive(Clone, Copy)]
+struct Window {
+    start: f64,
+    end: f64,
+    fade_in: f64,
+    fade_out: f64,
+}
 
-  const btnSubmit = document.getElementById('btnSubmit');
-  const waitNote  = document.getElementById('waitNote');
+fn default_opacity() -> f64 {
+    1.0
+}
 
-  let stream = null, mr = null, chunks = [], recordedBlob = null, recTimer = null, startTs = 0;
-  const MAX_MS = 30000;
+impl Overlay {
+    fn ext(&self) -> String {
+        self.path
+            .extension()
+            .and_then(OsStr::to_str)
+            .unwrap_or("")
+            .to_ascii_lowercase()
+    }
 
-  function setBusy(b){
-    btnSubmit.disabled = b;
-    btnSubmit.textContent = b ? 'Memproses…' : 'Generate';
-    waitNote.style.display = b ? 'block' : 'none';
-  }
-  function fmt(ms){
-    const s = Math.floor(ms/1000);
-    const m = String(Math.floor(s/60)).padStart(2,'0');
-    const ss = String(s%60).padStart(2,'0');
-    return m+':'+ss;
-  }
-  function startTimer(){
-    startTs = Date.now();
-    stopTimer();
-    recTimer = setInterval(()=>{ timerEl.textContent = fmt(Date.now()-startTs); }, 200);
-  }
-  function stopTimer(){
-    if (recTimer) { clearInterval(recTimer); recTimer = null; }
-  }
-  function stopStream(){
-    if (stream){
-      stream.getTracks().forEach(t=>t.stop());
-      stream = null;
-      cam.srcObject = null;
+    fn source(&self) -> OverlaySource {
+        self.source.unwrap_or_else(|| match self.ext().as_str() {
+            "gif" | "apng" | "webm" => OverlaySource::Loop,
+            _ => OverlaySource::Still,
+        })
     }
-    btnRec.disabled = true;
-    btnStop.disabled = true;
-  }
-  function switchPane(){
-    const v = form.source.value;
-    uploadPane.hidden = v !== 'upload';
-    recordPane.hidden = v !== 'record';
-    if (v !== 'record') {
-      stopStream();
-      playback.hidden = true;
-      recordedBlob = null;
-      timerEl.textContent = '00:00';
+
+    fn is_animated(&self) -> bool {
+        self.source() != OverlaySource::Still
     }
-  }
-  srcRadios.forEach(r=>r.addEventListener('change', switchPane));
-  switchPane();
 
-  // ===== Kamera =====
-  btnOpen.addEventListener('click', async ()=>{
-    try {
-      stream = await navigator.mediaDevices.getUserMedia({
-        video: { facingMode: 'user', width: {ideal: 720}, height: {ideal: 1280} },
-        audio: true
-      });
-      cam.srcObject = stream;
-      btnRec.disabled = false;
-    } catch (e){
-      alert('Tidak bisa mengakses kamera/mikrofon: ' + (e.message||e));
+    // Selesaikan start/end terhadap durasi output. None = tidak tampil sama sekali
+    // (mis. start di luar video pendek). Fade dipendekkan bila jendela lebih sempit.
+    fn window(&self, dur: f64) -> Option<Window> {
+        let start = self.start.resolve(dur);
+        let end = self.end.resolve(dur).min(dur);
+        if end - start < 0.05 {
+            return None;
+        }
+        let fade_in = self.fade_in.min(end - start);
+        let fade_out = self.fade_out.min(end - start - fade_in);
+        Some(Window { start, end, fade_in, fade_out })
     }
-  });
 
-  btnRec.addEventListener('click', ()=>{
-    if (!stream) return;
-    if (!('MediaRecorder' in window)) {
-      alert('Browser Anda belum mendukung MediaRecorder. Gunakan Chrome/Edge/Firefox terbaru, atau upload file.');
-      return;
+    // Filter tambahan di stream overlay gambar: opacity lalu fade alpha. Timestamp
+    // stream overlay sudah sejajar dengan video utama, jadi `st` memakai waktu absolut.
+    fn alpha_filters(&self, win: &Window) -> String {
+        let mut f = String::new();
+        if self.opacity < 1.0 || win.fade_in > 0.0 || win.fade_out > 0.0 {
+            f.push_str(",format=rgba");
+        }
+        if self.opacity < 1.0 {
+            f.push_str(&format!(",colorchannelmixer=aa={:.3}", self.opacity));
+        }
+        if win.fade_in > 0.0 {
+            f.push_str(&format!(
+                ",fade=t=in:st={:.3}:d={:.3}:alpha=1",
+                win.start, win.fade_in
+            ));
+        }
+        if win.fade_out > 0.0 {
+            f.push_str(&format!(
+                ",fade=t=out:st={:.3}:d={:.3}:alpha=1",
+                win.end - win.fade_out,
+                win.fade_out
+            ));
+        }
+        f
     }
-    // Prefer MP4 (Safari), fallback WEBM
-    let mime = null;
-    if (MediaRecorder.isTypeSupported('video/mp4;codecs=avc1')) mime = 'video/mp4;codecs=avc1';
-    else if (MediaRecorder.isTypeSupported('video/mp4'))       mime = 'video/mp4';
-    else if (MediaRecorder.isTypeSupported('video/webm;codecs=vp9')) mime = 'video/webm;codecs=vp9';
-    else if (MediaRecorder.isTypeSupported('video/webm;codecs=vp8')) mime = 'video/webm;codecs=vp8';
-    else mime = 'video/webm';
 
-    chunks = [];
-    recordedBlob = null;
-    mr = new MediaRecorder(stream, { mimeType: mime, videoBitsPerSecond: 2_500_000 });
-    mr.ondataavailable = e => { if (e.data && e.data.size) chunks.push(e.data); };
-    mr.onstop = ()=>{
-      stopTimer();
-      recordedBlob = new Blob(chunks, { type: mr.mimeType || 'video/webm' });
-      chunks = [];
-      playback.src = URL.createObjectURL(recordedBlob);
-      playback.hidden = false;
-      btnRec.disabled = false;
-      btnStop.disabled = true;
-    };
-    mr.start(250);
-    btnRec.disabled = true;
-    btnStop.disabled = false;
-    startTimer();
-    setTimeout(()=>{ if (mr && mr.state === 'recording') mr.stop(); }, MAX_MS);
-  });
+    // Padanan alpha_filters untuk drawtext (opsi `alpha` menerima ekspresi waktu).
+    fn text_alpha(&self, win: &Window) -> Option<String> {
+        if self.opacity >= 1.0 && win.fade_in <= 0.0 && win.fade_out <= 0.0 {
+            return None;
+        }
+        let mut expr = format!("{:.3}", self.opacity);
+        if win.fade_in > 0.0 {
+            expr.push_str(&format!(
+                "*clip((t-{:.3})/{:.3}\\,0\\,1)",
+                win.start, win.fade_in
+            ));
+        }
+        if win.fade_out > 0.0 {
+            expr.push_str(&format!(
+                "*clip(({:.3}-t)/{:.3}\\,0\\,1)",
+                win.end, win.fade_out
+            ));
+        }
+        Some(expr)
+    }
 
-  btnStop.addEventListener('click', ()=>{
-    if (mr && mr.state === 'recording') mr.stop();
-  });
+    // Ekspresi waktu untuk satu properti keyframe: sebelum keyframe pertama bernilai
+    // awal, sesudah yang terakhir bernilai akhir. None bila tak ada keyframe yang mengisinya.
+    fn motion(&self, start: f64, pick: impl Fn(&Keyframe) -> Option<String>) -> Option<String> {
+        let pts: Vec<(f64, String, Ease)> = self
+            .keyframes
+            .iter()
+            .filter_map(|k| pick(k).map(|v| (start + k.t, v, k.ease)))
+            .collect();
+        let mut expr = pts.last()?.1.clone();
+        for seg in pts.windows(2).rev() {
+            let ((t0, v0, ease), (t1, v1, _)) = (&seg[0], &seg[1]);
+            let p = format!("clip((t-{t0:.3})/{:.3}\\,0\\,1)", t1 - t0);
+            expr = format!(
+                "if(lt(t\\,{t1:.3})\\,{v0}+({v1}-{v0})*{}\\,{expr})",
+                ease.apply(&p)
+            );
+        }
+        Some(expr)
+    }
 
-  // ===== Submit: Upload biasa (MP4) =====
-  form.addEventListener('submit', (e)=>{
-    if (form.source.value === 'record') return; // record ditangani handler di bawah
-    setBusy(true); // cegah double submit
+    // Nilai akhir satu properti keyframe, untuk foto (gerak dianggap sudah selesai).
+    fn settled(&self, pick: impl Fn(&Keyframe) -> Option<String>) -> Option<String> {
+        self.keyframes.iter().filter_map(pick).next_back()
+    }
+
+    // Nama singkat untuk pesan error/log.
+    fn name(&self) -> String {
+        match &self.typ {
+            OverlayType::Text(tb) => format!("teks `{}`", tb.field),
+            _ => self
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| self.path.display().to_string()),
+        }
+    }
+
+    // Overlay gambar/video butuh input `-i` sendiri; overlay teks tidak.
+    fn has_input(&self) -> bool {
+        !matches!(self.typ, OverlayType::Text(_))
+    }
+
+    // Argumen input ffmpeg (termasuk `-i`) sesuai jenis sumber overlay.
+    fn input_args(&self, fps: u32) -> Vec<String> {
+        let source = self.source();
+        // ignore_loop=0 -> ikuti loop di file (umumnya tak terbatas)
+        let ignore_loop = if source == OverlaySource::Loop { "0" } else { "1" };
+        let mut args = match (source, self.ext().as_str()) {
+            (OverlaySource::Still, _) => to_args(&["-loop", "1", "-framerate", &fps.to_string()]),
+            (_, "gif") => to_args(&["-ignore_loop", ignore_loop]),
+            (_, "png" | "apng") => to_args(&["-f", "apng", "-ignore_loop", ignore_loop]),
+            // decoder native vp9 membuang alpha, jadi paksa libvpx
+            (OverlaySource::Loop, "webm") => {
+                to_args(&["-stream_loop", "-1", "-c:v", "libvpx-vp9"])
+            }
+            (_, "webm") => to_args(&["-c:v", "libvpx-vp9"]),
+            (OverlaySource::Loop, _) => to_args(&["-stream_loop", "-1"]),
+            (_, _) => Vec::new(),
+        };
+        args.extend(["-i".into(), path_arg(&self.path)]);
+        args
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum FillMode {
+    Crop,
+    Pad,
+    // pad di atas salinan video yang di-cover, diburamkan dan digelapkan
+    Blur,
+}
+
+// Background implisit untuk fill = "blur" bila template tidak mengisi [background].
+static FILL_BLUR: Background = Background::Blur { sigma: 20.0, dim: 0.25 };
+
+// Kanvas output per template. Nilai default = konstanta global di atas.
+// `aspect` (mis. "4:5") boleh dipakai untuk menurunkan sisi yang tidak diisi.
+#[derive(Clone, Deserialize, Serialize)]
+struct OutputProfile {
+    width: Option<i32>,
+    height: Option<i32>,
+    #[serde(default, skip_serializing)]
+    aspect: Option<String>,
+    #[serde(default = "default_fill")]
+    fill: FillMode,
+    #[serde(default = "default_fps")]
+    fps: u32,
+}
+
+fn default_fill() -> FillMode {
+    FILL_MODE
+}
+
+fn default_fps() -> u32 {
+    OUT_FPS
+}
+
+impl Default for OutputProfile {
+    fn default() -> Self {
+        OutputProfile {
+            width: Some(OUT_WIDTH),
+            height: Some(OUT_HEIGHT),
+            aspect: None,
+            fill: FILL_MODE,
+            fps: OUT_FPS,
+        }
+    }
+}
+
+impl OutputProfile {
+    fn w(&self) -> i32 {
+        self.width.unwrap_or(OUT_WIDTH)
+    }
+
+    fn h(&self) -> i32 {
+        self.height.unwrap_or(OUT_HEIGHT)
+    }
+
+    // Lengkapi width/height dari aspect, lalu pastikan ukurannya bisa di-encode.
+    fn normalize(&mut self) -> anyhow::Result<()> {
+        if let Some(aspect) = self.aspect.as_deref() {
+            let (aw, ah) = aspect
+                .split_once(':')
+                .and_then(|(a, b)| {
+                    Some((a.trim().parse::<f64>().ok()?, b.trim().parse::<f64>().ok()?))
+                })
+                .filter(|(a, b)| *a > 0.0 && *b > 0.0)
+                .ok_or_else(|| anyhow::anyhow!("aspect `{}` harus berbentuk W:H", aspect))?;
+            let even = |v: f64| ((v / 2.0).round() * 2.0) as i32;
+            match (self.width, self.height) {
+                (Some(w), None) => self.height = Some(even(w as f64 * ah / aw)),
+                (None, Some(h)) => self.width = Some(even(h as f64 * aw / ah)),
+                (None, None) => {
+                    self.width = Some(OUT_WIDTH);
+                    self.height = Some(even(OUT_WIDTH as f64 * ah / aw));
+                }
+                (Some(_), Some(_)) => anyhow::bail!("isi aspect atau width+height, bukan keduanya"),
+            }
+        }
+        let (w, h) = (self.w(), self.h());
+        if !(16..=4096).contains(&w) || !(16..=4096).contains(&h) || w % 2 != 0 || h % 2 != 0 {
+            anyhow::bail!("ukuran output {}x{} tidak valid (genap, 16..4096)", w, h);
+        }
+        if !(1..=60).contains(&self.fps) {
+            anyhow::bail!("fps {} tidak valid (1..60)", self.fps);
+        }
+        self.width = Some(w);
+        self.height = Some(h);
+        Ok(())
+    }
+}
+
+// Jendela video peserta di dalam kanvas (bingkai dengan lubang di tengah). Video
+// di-fit ke kotak ini memakai `fill` output; art template digambar di sekelilingnya.
+#[derive(Clone, Deserialize, Serialize)]
+struct VideoSlot {
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+    // sudut membulat (px); diabaikan bila ada mask
+    #[serde(default)]
+    radius: i32,
+    // "circle" atau path PNG grayscale: putih = video terlihat, hitam = tembus
+    mask: Option<SlotMask>,
+}
+
+// Bentuk video di dalam slot. Lingkaran mengikuti kotak slot (elips bila w != h).
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(from = "PathBuf", into = "PathBuf")]
+enum SlotMask {
+    Circle,
+    Image(PathBuf),
+}
+
+impl From<PathBuf> for SlotMask {
+    fn from(p: PathBuf) -> Self {
+        if p.as_os_str() == "circle" {
+            SlotMask::Circle
+        } else {
+            SlotMask::Image(p)
+        }
+    }
+}
+
+impl From<SlotMask> for PathBuf {
+    fn from(m: SlotMask) -> Self {
+        match m {
+            SlotMask::Circle => PathBuf::from("circle"),
+            SlotMask::Image(p) => p,
+        }
+    }
+}
+
+// Lapisan di bawah video: terlihat di bar letterbox mode pad dan di sekitar slot.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Background {
+    // gambar di-scale cover ke kanvas
+    Image { path: PathBuf },
+    // video (MP4/WebM) di-loop dan di-scale cover ke kanvas
+    Video { path: PathBuf },
+    // salinan video (cover) yang diburamkan; `dim` 0..1 menggelapkan
+    Blur {
+        #[serde(default = "default_blur_sigma")]
+        sigma: f64,
+        #[serde(default)]
+        dim: f64,
+    },
+}
+
+fn default_blur_sigma() -> f64 {
+    20.0
+}
+
+impl Background {
+    // file yang butuh input `-i` sendiri
+    fn file(&self) -> Option<&Path> {
+        match self {
+            Background::Image { path } | Background::Video { path } => Some(path),
+            Background::Blur { .. } => None,
+        }
+    }
+
+    fn input_args(&self, fps: u32) -> Vec<String> {
+        let (mut args, path) = match self {
+            Background::Image { path } => {
+                (to_args(&["-loop", "1", "-framerate", &fps.to_string()]), path)
+            }
+            Background::Video { path } => (to_args(&["-stream_loop", "-1"]), path),
+            Background::Blur { .. } => return Vec::new(),
+        };
+        args.extend(["-i".into(), path_arg(path)]);
+        args
+    }
+}
+
+// Mode foto (sumber `photo`): peserta upload gambar, hasilnya gambar berbingkai.
+// Foto yang diupload sebagai video dijadikan klip Ken Burns (zoom/pan pelan).
+#[derive(Clone, Deserialize, Serialize)]
+struct PhotoProfile {
+    #[serde(default)]
+    format: PhotoFormat,
+    // panjang klip Ken Burns (detik); kosong = durasi template
+    duration: Option<f64>,
+    // zoom di akhir klip (1 = diam)
+    #[serde(default = "default_photo_zoom")]
+    zoom: f64,
+}
+
+fn default_photo_zoom() -> f64 {
+    1.15
+}
+
+impl Default for PhotoProfile {
+    fn default() -> Self {
+        PhotoProfile { format: PhotoFormat::default(), duration: None, zoom: default_photo_zoom() }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum PhotoFormat {
+    #[default]
+    Png,
+    #[serde(alias = "jpeg")]
+    Jpg,
+}
+
+impl PhotoFormat {
+    fn ext(self) -> &'static str {
+        match self {
+            PhotoFormat::Png => "png",
+            PhotoFormat::Jpg => "jpg",
+        }
+    }
+}
+
+const PHOTO_EXTS: [&str; 6] = ["jpg", "jpeg", "png", "heic", "heif", "webp"];
+
+// Upload audio saja (podcast/radio): ditampilkan sebagai gelombang (`waves`) atau
+// spektrum (`freqs`) di region x/y/w/h, di atas background template.
+#[derive(Clone, Deserialize, Serialize)]
+struct Visualizer {
+    #[serde(default)]
+    style: VizStyle,
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+    #[serde(default = "default_viz_color")]
+    color: String,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum VizStyle {
+    #[default]
+    Waves,
+    Freqs,
+}
+
+fn default_viz_color() -> String {
+    "white".into()
+}
+
+const AUDIO_UPLOAD_EXTS: [&str; 3] = ["mp3", "m4a", "ogg"];
+
+impl Visualizer {
+    // latar hasil showwaves/showfreqs transparan, jadi bisa langsung di-overlay
+    fn filter(&self, fps: u32) -> String {
+        let (w, h, color) = (self.w, self.h, &self.color);
+        match self.style {
+            VizStyle::Waves => format!(
+                "showwaves=s={w}x{h}:mode=cline:draw=full:rate={fps}:colors={color},format=rgba"
+            ),
+            // showfreqs tidak punya opsi rate; laju frame mengikuti ukuran blok audio
+            VizStyle::Freqs => format!(
+                "showfreqs=s={w}x{h}:mode=bar:ascale=sqrt:fscale=log:colors={color},\
+                 fps={fps},format=rgba"
+            ),
+        }
+    }
+}
+
+// Green screen: warna `color` dibuang dari video peserta sehingga peserta tampil di
+// atas background template (gambar/video).
+#[derive(Clone, Deserialize, Serialize)]
+struct ChromaKey {
+    #[serde(default = "default_chroma_color")]
+    color: String,
+    // 0.01..1: seberapa jauh warna dari `color` masih ikut dibuang
+    #[serde(default = "default_chroma_similarity")]
+    similarity: f64,
+    // 0..1: kehalusan tepi
+    #[serde(default = "default_chroma_blend")]
+    blend: f64,
+}
+
+fn default_chroma_color() -> String {
+    "0x00FF00".into()
+}
+
+fn default_chroma_similarity() -> f64 {
+    0.3
+}
+
+fn default_chroma_blend() -> f64 {
+    0.1
+}
+
+impl ChromaKey {
+    // dipasang sebelum scale/LUT supaya warna yang di-key masih asli
+    fn filter(&self) -> String {
+        format!(
+            "chromakey=color={}:similarity={:.3}:blend={:.3},",
+            self.color.replace('#', "0x"),
+            self.similarity,
+            self.blend
+        )
+    }
+}
+
+// Color grading per template: 3D LUT (.cube) yang dibawa template, diterapkan ke
+// video peserta setelah di-scale. `strength` < 1 mencampur hasilnya dengan video asli.
+#[derive(Clone, Deserialize, Serialize)]
+struct Lut {
+    path: PathBuf,
+    #[serde(default = "default_opacity")]
+    strength: f64,
+}
+
+impl Lut {
+    // Filter grading (diawali koma) untuk disisipkan di chain video; `tag` membuat
+    // label split/blend unik per video. Blend parsial memakai gbrap supaya alpha
+    // video (mis. hasil chroma key) tidak hilang.
+    fn filters(&self, tag: usize) -> String {
+        let lut = format!("lut3d=file={}", filter_quote(&path_arg(&self.path)));
+        if self.strength >= 1.0 {
+            return format!(",{lut}");
+        }
+        format!(
+            ",format=gbrap,split[lo{tag}][li{tag}];[li{tag}]{lut}[lg{tag}];\
+             [lg{tag}][lo{tag}]blend=all_mode=normal:all_opacity={:.3}",
+            self.strength
+        )
+    }
+}
+
+// Musik latar template. `mode`: `mix` = musik di bawah suara peserta (di-duck saat
+// peserta bicara), `replace` = hanya musik, `original` = musik diabaikan.
+#[derive(Clone, Deserialize, Serialize)]
+struct Music {
+    path: PathBuf,
+    #[serde(default)]
+    mode: MusicMode,
+    #[serde(default = "default_music_volume")]
+    volume: f64,
+    // sidechain: musik turun otomatis saat ada suara peserta (mode mix)
+    #[serde(default = "default_duck")]
+    duck: bool,
+    // mulai dari detik ke-N lagu
+    #[serde(default)]
+    offset: f64,
+    #[serde(default = "default_music_fade")]
+    fade_out: f64,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum MusicMode {
+    #[default]
+    Mix,
+    Replace,
+    Original,
+}
+
+fn default_music_volume() -> f64 {
+    0.35
+}
+
+fn default_duck() -> bool {
+    true
+}
+
+fn default_music_fade() -> f64 {
+    2.0
+}
+
+const MUSIC_EXTS: [&str; 4] = ["mp3", "m4a", "ogg", "wav"];
+
+impl Music {
+    // mode original = template tetap punya musik, tapi tidak dipakai saat render
+    fn active(&self) -> bool {
+        self.mode != MusicMode::Original
+    }
+
+    // Chain musik tanpa label output: mulai dari `offset`, dipotong ke durasi output
+    // (input di-loop dengan -stream_loop), lalu fade-out di akhir.
+    fn chain(&self, input: usize, dur: f64) -> String {
+        let mut s = format!(
+            "[{input}:a]atrim=start={:.3}:duration={dur:.3},asetpts=PTS-STARTPTS,\
+             aformat=sample_rates=48000:channel_layouts=stereo,volume={:.3}",
+            self.offset, self.volume
+        );
+        let fade = self.fade_out.min(dur);
+        if fade > 0.0 {
+            s += &format!(",afade=t=out:st={:.3}:d={fade:.3}", dur - fade);
+        }
+        s
+    }
+}
+
+// Klip pembuka/penutup brand yang disambung di sekitar hasil komposit peserta.
+#[derive(Clone, Deserialize, Serialize)]
+struct Bumpers {
+    intro: Option<PathBuf>,
+    outro: Option<PathBuf>,
+    // nama transisi xfade (fade, wipeleft, ...); kosong = disambung langsung
+    transition: Option<String>,
+    #[serde(default = "default_transition_duration")]
+    transition_duration: f64,
+    // (durasi, punya audio) intro dan outro, diisi sekali saat template divalidasi
+    #[serde(skip)]
+    probed: [Option<(f64, bool)>; 2],
+}
+
+fn default_transition_duration() -> f64 {
+    0.5
+}
+
+const CLIP_EXTS: [&str; 2] = ["mp4", "webm"];
+
+const XFADE_TRANSITIONS: [&str; 44] = [
+    "fade", "fadeblack", "fadewhite", "fadegrays", "dissolve", "distance", "pixelize",
+    "radial", "zoomin", "wipeleft", "wiperight", "wipeup", "wipedown", "wipetl", "wipetr",
+    "wipebl", "wipebr", "slideleft", "slideright", "slideup", "slidedown", "smoothleft",
+    "smoothright", "smoothup", "smoothdown", "circlecrop", "rectcrop", "circleopen",
+    "circleclose", "vertopen", "vertclose", "horzopen", "horzclose", "diagtl", "diagtr",
+    "diagbl", "diagbr", "hlslice", "hrslice", "vuslice", "vdslice", "hblur", "squeezeh",
+    "squeezev",
+];
+
+impl Bumpers {
+    // urut sesuai input ffmpeg: intro lalu outro
+    fn clips(&self) -> impl Iterator<Item = &PathBuf> {
+        self.intro.iter().chain(self.outro.iter())
+    }
+}
+
+// Template kolase: beberapa video peserta dalam satu kanvas. Isi `slots` untuk
+// layout custom, atau `count` (+ `cols`) untuk grid otomatis.
+#[derive(Clone, Deserialize, Serialize)]
+struct Collage {
+    #[serde(default)]
+    count: usize,
+    cols: Option<usize>,
+    // jarak antar sel grid (px)
+    #[serde(default)]
+    gap: i32,
+    // radius sudut sel grid
+    #[serde(default)]
+    radius: i32,
+    #[serde(default)]
+    slots: Vec<VideoSlot>,
+    // nomor slot (1..N) yang audionya dipakai; kosong = semua audio di-mix
+    audio_slot: Option<usize>,
+}
+
+const MAX_COLLAGE_VIDEOS: usize = 9;
+
+impl Collage {
+    // Lengkapi `slots` dari grid bila layout custom tidak diisi, lalu validasi.
+    fn layout(&mut self, out: &OutputProfile) -> anyhow::Result<()> {
+        if self.slots.is_empty() {
+            let n = self.count;
+            if !(2..=MAX_COLLAGE_VIDEOS).contains(&n) {
+                anyhow::bail!("count kolase harus 2..{}", MAX_COLLAGE_VIDEOS);
+            }
+            let cols = self
+                .cols
+                .unwrap_or_else(|| (n as f64).sqrt().ceil() as usize)
+                .clamp(1, n);
+            let rows = n.div_ceil(cols);
+            let even = |v: i32| v / 2 * 2;
+            let cw = even((out.w() - self.gap * (cols as i32 + 1)) / cols as i32);
+            let ch = even((out.h() - self.gap * (rows as i32 + 1)) / rows as i32);
+            for i in 0..n {
+                let (row, col) = (i / cols, i % cols);
+                // baris terakhir yang tidak penuh diletakkan di tengah
+                let in_row = cols.min(n - row * cols) as i32;
+                let shift = (cols as i32 - in_row) * (cw + self.gap) / 2;
+                self.slots.push(VideoSlot {
+                    x: self.gap + col as i32 * (cw + self.gap) + shift,
+                    y: self.gap + row as i32 * (ch + self.gap),
+                    w: cw,
+                    h: ch,
+                    radius: self.radius.min(cw.min(ch) / 2),
+                    mask: None,
+                });
+            }
+        }
+        if !(2..=MAX_COLLAGE_VIDEOS).contains(&self.slots.len()) {
+            anyhow::bail!("kolase butuh 2..{} slot", MAX_COLLAGE_VIDEOS);
+        }
+        for (i, slot) in self.slots.iter().enumerate() {
+            slot.check(out).with_context(|| format!("slot {}", i + 1))?;
+        }
+        if self.audio_slot.is_some_and(|a| a == 0 || a > self.slots.len()) {
+            anyhow::bail!("audio_slot harus 1..{}", self.slots.len());
+        }
+        Ok(())
+    }
+}
+
+impl VideoSlot {
+    // mask berupa file (butuh input `-i` sendiri)
+    fn mask_image(&self) -> Option<&Path> {
+        match &self.mask {
+            Some(SlotMask::Image(p)) => Some(p),
+            _ => None,
+        }
+    }
+
+    fn check(&self, out: &OutputProfile) -> anyhow::Result<()> {
+        if self.w < 16 || self.h < 16 || self.x < 0 || self.y < 0 {
+            anyhow::bail!("ukuran slot minimal 16x16 dengan x/y >= 0");
+        }
+        if self.x + self.w > out.w() || self.y + self.h > out.h() {
+            anyhow::bail!("slot keluar dari kanvas {}x{}", out.w(), out.h());
+        }
+        if self.radius < 0 || self.radius * 2 > self.w.min(self.h) {
+            anyhow::bail!("radius slot harus 0..{}", self.w.min(self.h) / 2);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+struct Template {
+    #[serde(skip_deserializing)]
+    key: String,
+    title: String,
+    #[serde(default)]
+    output: OutputProfile,
+    #[serde(default)]
+    slot: Option<VideoSlot>,
+    #[serde(default)]
+    collage: Option<Collage>,
+    #[serde(default)]
+    background: Option<Background>,
+    #[serde(default)]
+    lut: Option<Lut>,
+    #[serde(default)]
+    chroma: Option<ChromaKey>,
+    #[serde(default)]
+    music: Option<Music>,
+    #[serde(default)]
+    bumpers: Option<Bumpers>,
+    #[serde(default)]
+    photo: PhotoProfile,
+    #[serde(default)]
+    visualizer: Option<Visualizer>,
+    overlays: Vec<Overlay>,
+    // font yang dibawa template (nama -> path relatif folder template); teks
+    // tidak pernah memakai font sistem
+    #[serde(default)]
+    fonts: BTreeMap<String, PathBuf>,
+    // hasil validasi aset; template dengan masalah tidak ditawarkan ke peserta
+    #[serde(skip_deserializing)]
+    problems: Vec<String>,
+    // catatan yang tidak memblokir (mis. font tanpa emoji)
+    #[serde(skip_deserializing)]
+    warnings: Vec<String>,
+    // folder aset yang dipegang selama template (atau salinannya) masih dipakai
+    #[serde(skip)]
+    assets: Option<Arc<AssetDir>>,
+}
+
+impl Template {
+    fn is_usable(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    // Kotak video peserta, urut sesuai urutan field `video`. Kosong = satu video
+    // mengisi seluruh kanvas.
+    fn video_slots(&self) -> Vec<&VideoSlot> {
+        match &self.collage {
+            Some(c) => c.slots.iter().collect(),
+            None => self.slot.iter().collect(),
+        }
+    }
+
+    fn video_count(&self) -> usize {
+        self.video_slots().len().max(1)
+    }
+
+    // Durasi template: akhir overlay terjauh yang ditulis dalam detik absolut (default
+    // overlay = MAX_DURATION_SEC). Dipakai untuk klip dari foto.
+    fn duration(&self) -> f64 {
+        let end = self
+            .overlays
+            .iter()
+            .filter_map(|ol| match ol.end {
+                TimeSpec::Secs(s) => Some(s),
+                _ => None,
+            })
+            .fold(0.0, f64::max);
+        if end > 0.0 { end.min(MAX_DURATION_SEC) } else { MAX_DURATION_SEC }
+    }
+
+    // Background hanya terlihat bila video tidak menutup seluruh kanvas.
+    fn visible_background(&self) -> Option<&Background> {
+        // chroma key dan visualizer audio selalu memperlihatkan kanvas di belakangnya
+        if self.chroma.is_some() || self.visualizer.is_some() {
+            return self.background.as_ref();
+        }
+        match self.output.fill {
+            FillMode::Blur => Some(self.background.as_ref().unwrap_or(&FILL_BLUR)),
+            FillMode::Pad => self.background.as_ref(),
+            FillMode::Crop => self.background.as_ref().filter(|_| !self.video_slots().is_empty()),
+        }
+    }
+
+    // Kolom teks yang perlu diisi peserta (unik per nama field).
+    fn text_fields(&self) -> Vec<&TextBox> {
+        let mut out: Vec<&TextBox> = Vec::new();
+        for ol in &self.overlays {
+            if let OverlayType::Text(tb) = &ol.typ {
+                if !out.iter().any(|t| t.field == tb.field) {
+                    out.push(tb);
+                }
+            }
+        }
+        out
+    }
+}
+
+// Katalog aktif. Reload menukar seluruh Arc sekaligus; render yang sedang jalan
+// tetap memegang snapshot lama sampai selesai.
+static TEMPLATES: Lazy<RwLock<Arc<Vec<Template>>>> =
+    Lazy::new(|| RwLock::new(Arc::new(load_catalog())));
+
+fn templates() -> Arc<Vec<Template>> {
+    TEMPLATES.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn load_catalog() -> Vec<Template> {
+    let (mut templates, errors) = load_templates(Path::new(TEMPLATES_DIR));
+    for e in &errors {
+        eprintln!("[templates] {:#}", e);
+    }
+    for tpl in &mut templates {
+        tpl.problems = validate_template(tpl);
+        for p in &tpl.problems {
+            eprintln!("[templates] {}: {}", tpl.key, p);
+        }
+        for w in &tpl.warnings {
+            eprintln!("[templates] {}: peringatan: {}", tpl.key, w);
+        }
+    }
+    let broken = templates.iter().filter(|t| !t.is_usable()).count();
+    println!(
+        "Templates: {} loaded, {} rejected, {} hidden (aset bermasalah)",
+        templates.len() - broken,
+        errors.len(),
+        broken
+    );
+    templates
+}
+
+// Publish, hapus, dan reload katalog tidak boleh saling menyela.
+static CATALOG_LOCK: Mutex<()> = Mutex::new(());
+
+fn reload_templates() {
+    let _guard = CATALOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    reload_locked();
+}
+
+// Seperti `reload_templates`, untuk pemanggil yang sudah memegang CATALOG_LOCK.
+fn reload_locked() {
+    let fresh = Arc::new(load_catalog());
+    *TEMPLATES.write().unwrap_or_else(|e| e.into_inner()) = fresh;
+}
+
+// ====== REVISI ASET TEMPLATE ======
+// Template dari admin API disimpan per revisi: templates/<key>/<rev>/, dengan file
+// `current` berisi nama revisi aktif. Path aset di Template menunjuk ke folder
+// revisinya, jadi revisi lama cukup dibiarkan sampai render terakhir yang
+// memakainya selesai, baru dihapus.
+const CURRENT_REV: &str = "current";
+
+// Folder aset yang sedang dipakai katalog / render. Kalau sudah dipensiunkan,
+// folder dihapus saat pemegang terakhir di-drop.
+#[derive(Debug)]
+struct AssetDir {
+    path: PathBuf,
+    // folder lama tanpa revisi (manifest langsung di templates/<key>/)
+    legacy: bool,
+    retired: AtomicBool,
+}
+
+static ASSET_DIRS: Lazy<Mutex<HashMap<PathBuf, Weak<AssetDir>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+impl AssetDir {
+    fn acquire(path: &Path, legacy: bool) -> Arc<AssetDir> {
+        let mut dirs = ASSET_DIRS.lock().unwrap_or_else(|e| e.into_inner());
+        dirs.retain(|_, d| d.strong_count() > 0);
+        if let Some(dir) = dirs.get(path).and_then(Weak::upgrade) {
+            return dir;
+        }
+        let dir = Arc::new(AssetDir {
+            path: path.to_path_buf(),
+            legacy,
+            retired: AtomicBool::new(false),
+        });
+        dirs.insert(path.to_path_buf(), Arc::downgrade(&dir));
+        dir
+    }
+
+    // Tandai folder untuk dihapus; langsung dihapus bila tidak ada yang memakainya.
+    fn retire(path: &Path, legacy: bool) {
+        let live = ASSET_DIRS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(path)
+            .and_then(Weak::upgrade);
+        match live {
+            Some(dir) => dir.retired.store(true, Ordering::SeqCst),
+            None => remove_assets(path, legacy),
+        }
+    }
+}
+
+impl Drop for AssetDir {
+    fn drop(&mut self) {
+        if self.retired.load(Ordering::SeqCst) {
+            remove_assets(&self.path, self.legacy);
+        }
+    }
+}
+
+fn remove_assets(path: &Path, legacy: bool) {
+    if legacy {
+        // sisa folder lama: hapus semua kecuali pointer dan folder revisi
+        if let Ok(rd) = std::fs::read_dir(path) {
+            for entry in rd.filter_map(Result::ok) {
+                let p = entry.path();
+                if is_revision_entry(&p) || entry.file_name() == CURRENT_REV {
+                    continue;
+                }
+                let _ = if p.is_dir() {
+                    std::fs::remove_dir_all(&p)
+                } else {
+                    std::fs::remove_file(&p)
+                };
+            }
+        }
+        let _ = std::fs::remove_dir(path);
+    } else {
+        let _ = std::fs::remove_dir_all(path);
+        // folder key ikut dihapus kalau template sudah dihapus dan kosong
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::remove_dir(parent);
+        }
+    }
+}
+
+fn is_revision_name(name: &str) -> bool {
+    name.len() == 21 && name.starts_with('r') && name[1..].bytes().all(|b| b.is_ascii_digit())
+}
+
+fn is_revision_entry(path: &Path) -> bool {
+    path.is_dir() && path.file_name().and_then(OsStr::to_str).is_some_and(is_revision_name)
+}
+
+fn new_revision_name() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    format!("r{:020}", nanos)
+}
+
+// Revisi aktif dari pointer `current`, kalau template memakai format revisi.
+fn current_revision(dir: &Path) -> Option<String> {
+    let rev = std::fs::read_to_string(dir.join(CURRENT_REV)).ok()?;
+    let rev = rev.trim();
+    is_revision_name(rev).then(|| rev.to_string())
+}
+
+// Folder aset yang sedang hidup untuk key ini (revisi aktif atau format lama).
+fn live_dir(key: &str) -> Option<PathBuf> {
+    let dir = template_dir(key);
+    match current_revision(&dir) {
+        Some(rev) => Some(dir.join(rev)),
+        None => MANIFEST_NAMES
+            .iter()
+            .any(|n| dir.join(n).is_file())
+            .then_some(dir),
+    }
+}
+
+// Pensiunkan revisi (dan sisa format lama) yang bukan revisi aktif. Mengembalikan
+// false bila folder hanya berisi revisi tanpa pointer, yaitu template yang sudah
+// dihapus tapi asetnya masih dipakai render.
+fn retire_stale(dir: &Path) -> bool {
+    let current = current_revision(dir);
+    let Ok(rd) = std::fs::read_dir(dir) else { return true };
+    let (mut revs, mut others) = (Vec::new(), false);
+    for entry in rd.filter_map(Result::ok) {
+        let p = entry.path();
+        if is_revision_entry(&p) {
+            revs.push(p);
+        } else if entry.file_name() != CURRENT_REV {
+            others = true;
+        }
+    }
+    if current.is_none() && (revs.is_empty() || others) {
+        return true;
+    }
+    for rev in revs {
+        if current.as_deref() != rev.file_name().and_then(OsStr::to_str) {
+            AssetDir::retire(&rev, false);
+        }
+    }
+    if current.is_some() && others {
+        AssetDir::retire(dir, true);
+    }
+    current.is_some()
+}
+
+fn valid_template_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// Path di manifest harus tetap di dalam folder template (tanpa absolut / `..`).
+fn resolve_asset(dir: &Path, rel: &Path) -> anyhow::Result<PathBuf> {
+    if rel
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        anyhow::bail!("path `{}` harus relatif di dalam folder template", rel.display());
+    }
+    Ok(dir.join(rel))
+}
+
+fn parse_manifest(path: &Path) -> anyhow::Result<Template> {
+    let raw = std::fs::read_to_string(path)
+        .with_context(|| format!("gagal membaca {}", path.display()))?;
+    let tpl: Template = if path.extension() == Some(OsStr::new("json")) {
+        serde_json::from_str(&raw).with_context(|| format!("{} bukan JSON yang valid", path.display()))?
+    } else {
+        toml::from_str(&raw).with_context(|| format!("{} bukan TOML yang valid", path.display()))?
+    };
+    Ok(tpl)
+}
+
+fn load_template(dir: &Path) -> anyhow::Result<Template> {
+    let key = dir
+        .file_name()
+        .and_then(OsStr::to_str)
+        .unwrap_or_default();
+    if !valid_template_key(key) {
+        anyhow::bail!(
+            "{}: nama folder hanya boleh huruf, angka, '-' dan '_'",
+            dir.display()
+        );
+    }
+    let (assets, legacy) = match current_revision(dir) {
+        Some(rev) => (dir.join(rev), false),
+        None => (dir.to_path_buf(), true),
+    };
+    let mut tpl = load_template_as(&assets, key)?;
+    tpl.assets = Some(AssetDir::acquire(&assets, legacy));
+    Ok(tpl)
+}
+
+// Seperti `load_template`, tapi key tidak diambil dari nama folder
+// (dipakai admin API saat memvalidasi folder staging).
+fn load_template_as(dir: &Path, key: &str) -> anyhow::Result<Template> {
+    let manifest = MANIFEST_NAMES
+        .iter()
+        .map(|n| dir.join(n))
+        .find(|p| p.is_file())
+        .ok_or_else(|| anyhow::anyhow!("{}: manifest tidak ditemukan", dir.display()))?;
+
+    let mut tpl = parse_manifest(&manifest)?;
+    tpl.key = key.to_string();
+    tpl.output
+        .normalize()
+        .with_context(|| format!("{}: [output] tidak valid", manifest.display()))?;
+    if let Some(slot) = &tpl.slot {
+        slot.check(&tpl.output)
+            .with_context(|| format!("{}: [slot] tidak valid", manifest.display()))?;
+    }
+    if let Some(collage) = &mut tpl.collage {
+        if tpl.slot.is_some() {
+            anyhow::bail!("{}: pakai [slot] atau [collage], bukan keduanya", manifest.display());
+        }
+        collage
+            .layout(&tpl.output)
+            .with_context(|| format!("{}: [collage] tidak valid", manifest.display()))?;
+    }
+    if let Some(lut) = &mut tpl.lut {
+        lut.path = resolve_asset(dir, &lut.path)
+            .with_context(|| format!("{}: lut tidak valid", manifest.display()))?;
+        if !(0.0..=1.0).contains(&lut.strength) {
+            anyhow::bail!("{}: strength lut harus 0..1", manifest.display());
+        }
+        check_cube(&lut.path)
+            .with_context(|| format!("{}: lut ditolak", manifest.display()))?;
+    }
+    if let Some(music) = &mut tpl.music {
+        music.path = resolve_asset(dir, &music.path)
+            .with_context(|| format!("{}: musik tidak valid", manifest.display()))?;
+        if !MUSIC_EXTS.contains(&lower_ext(&music.path.to_string_lossy()).as_str()) {
+            anyhow::bail!("{}: musik harus {}", manifest.display(), MUSIC_EXTS.join("/"));
+        }
+        if !(0.0..=2.0).contains(&music.volume) {
+            anyhow::bail!("{}: volume musik harus 0..2", manifest.display());
+        }
+        if music.offset < 0.0 || music.fade_out < 0.0 {
+            anyhow::bail!("{}: offset/fade_out musik tidak boleh negatif", manifest.display());
+        }
+    }
+    if let Some(b) = &mut tpl.bumpers {
+        if b.intro.is_none() && b.outro.is_none() {
+            anyhow::bail!("{}: [bumpers] butuh intro dan/atau outro", manifest.display());
+        }
+        for clip in [&mut b.intro, &mut b.outro].into_iter().flatten() {
+            *clip = resolve_asset(dir, clip)
+                .with_context(|| format!("{}: bumper tidak valid", manifest.display()))?;
+            if !CLIP_EXTS.contains(&lower_ext(&clip.to_string_lossy()).as_str()) {
+                anyhow::bail!("{}: bumper harus {}", manifest.display(), CLIP_EXTS.join("/"));
+            }
+        }
+        if let Some(t) = &b.transition {
+            if !XFADE_TRANSITIONS.contains(&t.as_str()) {
+                anyhow::bail!("{}: transisi bumper `{}` tidak dikenal", manifest.display(), t);
+            }
+        }
+        if !(0.1..=3.0).contains(&b.transition_duration) {
+            anyhow::bail!("{}: transition_duration harus 0.1..3 detik", manifest.display());
+        }
+    }
+    if let Some(viz) = &tpl.visualizer {
+        if tpl.collage.is_some() {
+            anyhow::bail!("{}: [visualizer] tidak bisa dipakai di kolase", manifest.display());
+        }
+        let (out_w, out_h) = (tpl.output.w(), tpl.output.h());
+        let inside = viz.x >= 0 && viz.y >= 0 && viz.x + viz.w <= out_w && viz.y + viz.h <= out_h;
+        if viz.w < 16 || viz.h < 16 || !inside {
+            anyhow::bail!(
+                "{}: [visualizer] minimal 16x16 dan di dalam kanvas {}x{}",
+                manifest.display(),
+                out_w,
+                out_h
+            );
+        }
+        if !valid_color(&viz.color) {
+            anyhow::bail!("{}: warna visualizer tidak valid", manifest.display());
+        }
+    }
+    if tpl.photo.duration.is_some_and(|d| !(1.0..=MAX_DURATION_SEC).contains(&d))
+        || !(1.0..=2.0).contains(&tpl.photo.zoom)
+    {
+        anyhow::bail!(
+            "{}: [photo]: duration 1..{} detik, zoom 1..2",
+            manifest.display(),
+            MAX_DURATION_SEC
+        );
+    }
+    match &mut tpl.background {
+        Some(Background::Image { path }) => {
+            *path = resolve_asset(dir, path)
+                .with_context(|| format!("{}: background tidak valid", manifest.display()))?;
+        }
+        Some(Background::Video { path }) => {
+            *path = resolve_asset(dir, path)
+                .with_context(|| format!("{}: background tidak valid", manifest.display()))?;
+            if !CLIP_EXTS.contains(&lower_ext(&path.to_string_lossy()).as_str()) {
+                let exts = CLIP_EXTS.join("/");
+                anyhow::bail!("{}: background video harus {}", manifest.display(), exts);
+            }
+        }
+        Some(Background::Blur { sigma, dim })
+            if !(1.0..=100.0).contains(sigma) || !(0.0..=1.0).contains(dim) =>
+        {
+            anyhow::bail!("{}: background blur: sigma 1..100, dim 0..1", manifest.display());
+        }
+        _ => {}
+    }
+    if let Some(chroma) = &tpl.chroma {
+        if tpl.background.as_ref().and_then(Background::file).is_none() {
+            anyhow::bail!(
+                "{}: [chroma] butuh [background] bertipe image atau video",
+                manifest.display()
+            );
+        }
+        if !valid_color(&chroma.color) {
+            anyhow::bail!("{}: warna chroma tidak valid", manifest.display());
+        }
+        if !(0.01..=1.0).contains(&chroma.similarity) || !(0.0..=1.0).contains(&chroma.blend) {
+            anyhow::bail!("{}: chroma: similarity 0.01..1, blend 0..1", manifest.display());
+        }
+    }
+    if tpl.background.is_some() && tpl.visible_background().is_none() {
+        tpl.warnings
+            .push("background tidak terlihat pada fill = \"crop\" tanpa slot".into());
+    }
+    let slots = tpl.slot.iter_mut().chain(tpl.collage.iter_mut().flat_map(|c| &mut c.slots));
+    for slot in slots {
+        if let Some(SlotMask::Image(mask)) = &mut slot.mask {
+            *mask = resolve_asset(dir, mask)
+                .with_context(|| format!("{}: mask slot tidak valid", manifest.display()))?;
+        }
+    }
+    if tpl.overlays.is_empty() {
+        anyhow::bail!("{}: minimal satu overlay", manifest.display());
+    }
+    for (name, path) in tpl.fonts.iter_mut() {
+        *path = resolve_asset(dir, path)
+            .with_context(|| format!("{}: font `{}` tidak valid", manifest.display(), name))?;
+        let notes = check_font(path)
+            .with_context(|| format!("{}: font `{}` ditolak", manifest.display(), name))?;
+        tpl.warnings
+            .extend(notes.into_iter().map(|n| format!("font `{name}`: {n}")));
+    }
+    for ol in &mut tpl.overlays {
+        if let OverlayType::Text(tb) = &ol.typ {
+            check_text_box(tb).with_context(|| format!("{}: overlay teks", manifest.display()))?;
+            if !tpl.fonts.contains_key(&tb.font) {
+                anyhow::bail!(
+                    "{}: font `{}` untuk teks `{}` tidak ada di [fonts]",
+                    manifest.display(),
+                    tb.font,
+                    tb.field
+                );
+            }
+        } else {
+            if ol.path.as_os_str().is_empty() {
+                anyhow::bail!("{}: overlay gambar wajib punya path", manifest.display());
+            }
+            ol.path = resolve_asset(dir, &ol.path)
+                .with_context(|| format!("{}: overlay tidak valid", manifest.display()))?;
+        }
+        // waktu relatif (end-3, 25%) baru bisa dicek saat render
+        let span = match (ol.start.secs(), ol.end.secs()) {
+            (Some(s), Some(e)) if e <= s => anyhow::bail!(
+                "{}: overlay {} punya end <= start",
+                manifest.display(),
+                ol.name()
+            ),
+            (Some(s), Some(e)) => e - s,
+            _ => f64::INFINITY,
+        };
+        if !(0.0..=1.0).contains(&ol.opacity)
+            || ol.fade_in < 0.0
+            || ol.fade_out < 0.0
+            || ol.fade_in + ol.fade_out > span
+        {
+            anyhow::bail!(
+                "{}: overlay {}: opacity harus 0..1 dan fade_in+fade_out tidak boleh melebihi durasi tampil",
+                manifest.display(),
+                ol.name()
+            );
+        }
+        check_keyframes(ol, span)
+            .with_context(|| format!("{}: overlay {}", manifest.display(), ol.name()))?;
+        if !ol.has_input() && ol.blend != BlendMode::Normal {
+            anyhow::bail!(
+                "{}: overlay {}: blend hanya untuk overlay gambar/video",
+                manifest.display(),
+                ol.name()
+            );
+        }
+    }
+    Ok(tpl)
+}
+
+// ================== TEMPLATE VALIDATION ==================
+struct VisualProbe {
+    width: i32,
+    height: i32,
+    pix_fmt: String,
+    // WebM VP9 menyimpan alpha di side data; ffprobe menandainya lewat tag alpha_mode
+    alpha_tag: bool,
+}
+
+// Info stream video pertama dari gambar maupun video (overlay, background, mask).
+// Versi sinkron (dipanggil dari loader, bukan dari handler async).
+fn probe_visual(ffprobe: &str, path: &Path) -> anyhow::Result<VisualProbe> {
+    let out = std::process::Command::new(ffprobe)
+        .args(["-v", "error", "-select_streams", "v:0"])
+        .args(["-show_entries", "stream=width,height,pix_fmt:stream_tags=alpha_mode"])
+        .args(["-of", "json"])
+        .arg(path.as_os_str())
+        .stderr(Stdio::piped())
+        .output()?;
+    if !out.status.success() {
+        anyhow::bail!("ffprobe gagal: {}", String::from_utf8_lossy(&out.stderr).trim());
+    }
+    let v: serde_json::Value = serde_json::from_slice(&out.stdout)?;
+    let st = v["streams"]
+        .get(0)
+        .ok_or_else(|| anyhow::anyhow!("tidak ada stream gambar/video"))?;
+    Ok(VisualProbe {
+        width: st["width"].as_i64().unwrap_or(0) as i32,
+        height: st["height"].as_i64().unwrap_or(0) as i32,
+        pix_fmt: st["pix_fmt"].as_str().unwrap_or("").to_string(),
+        alpha_tag: st["tags"]["alpha_mode"].as_str() == Some("1"),
+    })
+}
+
+// Durasi klip dan ada/tidaknya audio, dalam satu panggilan ffprobe.
+fn probe_clip(ffprobe: &str, path: &Path) -> anyhow::Result<(f64, bool)> {
+    let out = std::process::Command::new(ffprobe)
+        .args(["-v", "error", "-show_entries", "format=duration:stream=codec_type"])
+        .args(["-of", "json"])
+        .arg(path.as_os_str())
+        .stderr(Stdio::piped())
+        .output()?;
+    if !out.status.success() {
+        anyhow::bail!("ffprobe gagal: {}", String::from_utf8_lossy(&out.stderr).trim());
+    }
+    let v: serde_json::Value = serde_json::from_slice(&out.stdout)?;
+    let kinds: Vec<&str> = v["streams"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|s| s["codec_type"].as_str())
+        .collect();
+    if !kinds.contains(&"video") {
+        anyhow::bail!("tidak ada stream video");
+    }
+    let dur = v["format"]["duration"]
+        .as_str()
+        .and_then(|d| d.parse::<f64>().ok())
+        .unwrap_or(0.0);
+    if dur <= 0.0 {
+        anyhow::bail!("durasi tidak terbaca");
+    }
+    Ok((dur, kinds.contains(&"audio")))
+}
+
+fn probe_audio(ffprobe: &str, path: &Path) -> anyhow::Result<()> {
+    let out = std::process::Command::new(ffprobe)
+        .args(["-v", "error", "-select_streams", "a:0"])
+        .args(["-show_entries", "stream=codec_type", "-of", "csv=p=0"])
+        .arg(path.as_os_str())
+        .stderr(Stdio::piped())
+        .output()?;
+    if !out.status.success() {
+        anyhow::bail!("ffprobe gagal: {}", String::from_utf8_lossy(&out.stderr).trim());
+    }
+    if String::from_utf8_lossy(&out.stdout).trim().is_empty() {
+        anyhow::bail!("tidak ada stream audio");
+    }
+    Ok(())
+}
+
+// pal8 tidak dihitung di sini; lihat `palette_has_alpha`.
+fn pix_fmt_has_alpha(fmt: &str) -> bool {
+    fmt.starts_with("ya")
+        || fmt.starts_with("yuva")
+        || fmt.starts_with("gbrap")
+        || ["rgba", "bgra", "argb", "abgr"].iter().any(|a| fmt.contains(a))
+}
+
+// PNG berpalet didekode ffmpeg sebagai pal8, dengan atau tanpa transparansi. Alpha
+// hanya ada bila chunk tRNS muncul sebelum IDAT.
+fn palette_has_alpha(path: &Path) -> bool {
+    use std::io::{Read, Seek, SeekFrom};
+    let Ok(mut f) = std::fs::File::open(path) else { return false };
+    let mut sig = [0u8; 8];
+    if f.read_exact(&mut sig).is_err() {
+        return false;
+    }
+    if sig.starts_with(b"GIF8") {
+        return std::fs::read(path).is_ok_and(|data| gif_has_transparency(&data));
+    }
+    if sig != *b"\x89PNG\r\n\x1a\n" {
+        // container berpalet lain (BMP, TIFF, ...) tidak menyimpan alpha di palet
+        return false;
+    }
+    let mut head = [0u8; 8];
+    while f.read_exact(&mut head).is_ok() {
+        match &head[4..] {
+            b"tRNS" => return true,
+            b"IDAT" | b"IEND" => return false,
+            _ => {}
+        }
+        // lewati data + CRC
+        let len = u32::from_be_bytes([head[0], head[1], head[2], head[3]]);
+        if f.seek(SeekFrom::Current(len as i64 + 4)).is_err() {
+            return false;
+        }
+    }
+    false
+}
+
+// GIF transparan bila salah satu Graphic Control Extension menyalakan flag warna
+// transparan (GIF animasi bisa menyalakannya di frame mana saja).
+fn gif_has_transparency(data: &[u8]) -> bool {
+    // ukuran color table dari byte packed (flag di bit 7, ukuran di bit 0..2)
+    let table = |packed: u8| if packed & 0x80 != 0 { 3 << ((packed & 7) + 1) } else { 0 };
+    // lewati rangkaian sub-block sampai blok berukuran 0; None bila file terpotong
+    let sub_blocks = |mut i: usize| loop {
+        match data.get(i) {
+            Some(0) => return Some(i + 1),
+            Some(&n) => i += n as usize + 1,
+            None => return None,
+        }
+    };
+    // header 6 byte + logical screen descriptor 7 byte, lalu global color table
+    let Some(&packed) = data.get(10) else { return false };
+    let mut i = 13 + table(packed);
+    loop {
+        let next = match (data.get(i), data.get(i + 1)) {
+            (Some(0x21), Some(0xF9)) => match data.get(i + 3) {
+                Some(flags) if flags & 1 != 0 => return true,
+                Some(_) => sub_blocks(i + 2),
+                None => return false,
+            },
+            (Some(0x21), Some(_)) => sub_blocks(i + 2),
+            // image descriptor 10 byte, local color table, ukuran kode LZW, lalu data
+            (Some(0x2C), _) => match data.get(i + 9) {
+                Some(&packed) => sub_blocks(i + 10 + table(packed) + 1),
+                None => return false,
+            },
+            _ => return false,
+        };
+        let Some(next) = next else { return false };
+        i = next;
+    }
+}
+
+fn ffprobe_available() -> bool {
+    Path::new(&BINS.ffprobe).exists() || which("ffprobe").is_ok()
+}
+
+fn aspect_off(a: f64, b: f64) -> f64 {
+    (a / b - 1.0).abs()
+}
+
+// Cek ukuran gambar terhadap cara overlay itu di-scale di build_filter_graph.
+fn check_overlay_dims(typ: &OverlayType, w: i32, h: i32, out: &OutputProfile) -> Option<String> {
+    if w <= 0 || h <= 0 {
+        return Some("dimensi gambar tidak terbaca".into());
+    }
+    let (cw, ch) = (out.w(), out.h());
+    let img = w as f64 / h as f64;
+    match typ {
+        OverlayType::Full => {
+            let canvas = cw as f64 / ch as f64;
+            (aspect_off(img, canvas) > 0.02)
+                .then(|| format!("rasio {w}x{h} tidak sesuai kanvas {cw}x{ch}"))
+        }
+        OverlayType::Band { h: bh, .. } => {
+            let bh = bh.unwrap_or(160);
+            let band = cw as f64 / bh as f64;
+            (aspect_off(img, band) > 0.10)
+                .then(|| format!("rasio {w}x{h} tidak sesuai band {cw}x{bh}"))
+        }
+        OverlayType::Logo { w: lw, h: lh, .. } => {
+            let lw = lw.unwrap_or(220);
+            let out_h = match lh {
+                Some(v) if *v > 0 => *v,
+                _ => (lw as f64 / img).round() as i32,
+            };
+            (lw > cw || out_h > ch)
+                .then(|| format!("logo {lw}x{out_h} lebih besar dari kanvas"))
+        }
+        OverlayType::Text(_) => None,
+    }
+}
+
+// Kembalikan daftar masalah aset template (kosong = siap dipakai).
+// Info bumper hasil probe disimpan di template supaya render tidak perlu ffprobe lagi.
+fn validate_template(tpl: &mut Template) -> Vec<String> {
+    // tanpa ffprobe aset tidak bisa divalidasi dan durasi upload pun tidak terbaca
+    if !ffprobe_available() {
+        return vec!["ffprobe tidak ditemukan; aset template tidak bisa divalidasi".into()];
+    }
+    let mut problems = Vec::new();
+
+    for ol in &mut tpl.overlays {
+        let name = ol.name();
+        // font teks sudah dicek saat manifest dimuat
+        if !ol.has_input() {
+            continue;
+        }
+        if !ol.path.is_file() {
+            problems.push(format!("overlay {name}: file tidak ditemukan"));
+            continue;
+        }
+        match probe_visual(&BINS.ffprobe, &ol.path) {
+            Err(e) => problems.push(format!("overlay {name}: {e:#}")),
+            Ok(p) => {
+                let alpha = match p.pix_fmt.as_str() {
+                    "pal8" => palette_has_alpha(&ol.path),
+                    fmt => pix_fmt_has_alpha(fmt) || p.alpha_tag,
+                };
+                if !alpha {
+                    problems.push(format!(
+                        "overlay {name}: tidak punya alpha (pix_fmt {})",
+                        p.pix_fmt
+                    ));
+                }
+                if let Some(msg) = check_overlay_dims(&ol.typ, p.width, p.height, &tpl.output) {
+                    problems.push(format!("overlay {name}: {msg}"));
+                }
+                ol.probed = Some((p.width, p.height));
+            }
+        }
+    }
+    if let Some(bg) = tpl.background.as_ref().and_then(Background::file) {
+        if !bg.is_file() {
+            problems.push("background: file tidak ditemukan".into());
+        } else if let Err(e) = probe_visual(&BINS.ffprobe, bg) {
+            problems.push(format!("background: {e:#}"));
+        }
+    }
+    if let Some(music) = &tpl.music {
+        if !music.path.is_file() {
+            problems.push("musik: file tidak ditemukan".into());
+        } else if let Err(e) = probe_audio(&BINS.ffprobe, &music.path) {
+            problems.push(format!("musik: {e:#}"));
+        }
+    }
+    if let Some(b) = &mut tpl.bumpers {
+        let mut probed = [None, None];
+        for (info, clip) in probed.iter_mut().zip([&b.intro, &b.outro]) {
+            let Some(clip) = clip else { continue };
+            if !clip.is_file() {
+                problems.push(format!("bumper {}: file tidak ditemukan", clip.display()));
+                continue;
+            }
+            match probe_clip(&BINS.ffprobe, clip) {
+                Ok(p) => *info = Some(p),
+                Err(e) => problems.push(format!("bumper {}: {e:#}", clip.display())),
+            }
+        }
+        b.probed = probed;
+    }
+    for mask in tpl.video_slots().into_iter().filter_map(VideoSlot::mask_image) {
+        if !mask.is_file() {
+            problems.push(format!("mask slot {}: file tidak ditemukan", mask.display()));
+        } else if let Err(e) = probe_visual(&BINS.ffprobe, mask) {
+            problems.push(format!("mask slot {}: {e:#}", mask.display()));
+        }
+    }
+    problems
+}
+
+// Sidik jari isi folder template (path, ukuran, mtime) untuk mendeteksi perubahan.
+fn catalog_fingerprint(root: &Path) -> Vec<(PathBuf, u64, Option<SystemTime>)> {
+    let mut out = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(rd) = std::fs::read_dir(&dir) else { continue };
+        for entry in rd.filter_map(Result::ok) {
+            let path = entry.path();
+            let Ok(meta) = entry.metadata() else { continue };
+            if meta.is_dir() {
+                stack.push(path);
+            } else {
+                out.push((path, meta.len(), meta.modified().ok()));
+            }
+        }
+    }
+    out.sort();
+    out
+}
+
+// Polling sederhana tanpa dependency tambahan. Reload baru dijalankan setelah
+// isi folder stabil selama satu interval, supaya file yang masih disalin tidak
+// terbaca setengah jadi.
+fn spawn_template_watcher() {
+    std::thread::spawn(|| {
+        let root = Path::new(TEMPLATES_DIR);
+        let mut loaded = catalog_fingerprint(root);
+        let mut last = loaded.clone();
+        loop {
+            std::thread::sleep(TEMPLATE_POLL);
+            let now = catalog_fingerprint(root);
+            if now != loaded && now == last {
+                println!("[templates] perubahan terdeteksi, memuat ulang katalog");
+                reload_templates();
+                loaded = now.clone();
+            }
+            last = now;
+        }
+    });
+}
+
+fn check_text_box(tb: &TextBox) -> anyhow::Result<()> {
+    if tb.field.is_empty()
+        || !tb
+            .field
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        anyhow::bail!("field `{}` hanya boleh huruf kecil, angka dan '_'", tb.field);
+    }
+    if tb.w <= 0 || tb.h <= 0 {
+        anyhow::bail!("kotak teks `{}` harus punya w dan h > 0", tb.field);
+    }
+    if !(8..=400).contains(&tb.size) || !(0..=20).contains(&tb.stroke_width) {
+        anyhow::bail!("ukuran font/stroke teks `{}` di luar batas", tb.field);
+    }
+    if !(1..=5).contains(&tb.max_lines) {
+        anyhow::bail!("max_lines teks `{}` harus 1..5", tb.field);
+    }
+    for c in std::iter::once(&tb.color).chain(tb.stroke.as_ref()) {
+        if !valid_color(c) {
+            anyhow::bail!("warna `{}` tidak valid", c);
+        }
+    }
+    Ok(())
+}
+
+fn check_keyframes(ol: &Overlay, span: f64) -> anyhow::Result<()> {
+    if ol.keyframes.is_empty() {
+        return Ok(());
+    }
+    if !ol.has_input() {
+        anyhow::bail!("keyframes hanya didukung untuk overlay gambar/video");
+    }
+    let mut last = -1.0;
+    for k in &ol.keyframes {
+        if k.t <= last || k.t > span {
+            anyhow::bail!("keyframe t={} harus naik dan berada dalam 0..(end-start)", k.t);
+        }
+        last = k.t;
+        if k.scale.is_some_and(|s| !(s > 0.0 && s <= 10.0)) {
+            anyhow::bail!("keyframe t={}: scale harus > 0 dan <= 10", k.t);
+        }
+    }
+    Ok(())
+}
+
+// Glyph wajib: seluruh ASCII yang bisa dicetak (cukup untuk teks bahasa Indonesia).
+// Tanda baca tipografis dan emoji hanya dicatat sebagai peringatan; karakter
+// yang tidak ada akan dibuang saat render.
+const EXTRA_GLYPHS: &str = "’“”–…é";
+const EMOJI_SAMPLE: &str = "😀😂🙏👍❤🎉🔥✨";
+
+// Validasi file .cube: LUT_3D_SIZE ada dan jumlah baris data = size^3 (tiga angka).
+fn check_cube(path: &Path) -> anyhow::Result<()> {
+    if lower_ext(&path.to_string_lossy()) != "cube" {
+        anyhow::bail!("hanya file .cube yang didukung");
+    }
+    let text = std::fs::read_to_string(path).context("tidak bisa dibaca sebagai teks")?;
+    let mut size = None;
+    let mut rows = 0usize;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(v) = line.strip_prefix("LUT_3D_SIZE") {
+            size = Some(v.trim().parse::<usize>().context("LUT_3D_SIZE tidak valid")?);
+        } else if line.starts_with("LUT_1D_SIZE") {
+            anyhow::bail!("LUT 1D tidak didukung, gunakan LUT 3D");
+        } else if line.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            // TITLE, DOMAIN_MIN/MAX, dst.
+            continue;
+        } else {
+            let nums: Result<Vec<f64>, _> = line.split_whitespace().map(str::parse).collect();
+            if !matches!(nums, Ok(n) if n.len() == 3) {
+                anyhow::bail!("baris data tidak valid: `{}`", line);
+            }
+            rows += 1;
+        }
+    }
+    let size = size.ok_or_else(|| anyhow::anyhow!("LUT_3D_SIZE tidak ditemukan"))?;
+    if !(2..=256).contains(&size) {
+        anyhow::bail!("LUT_3D_SIZE {} di luar batas 2..256", size);
+    }
+    if rows != size.pow(3) {
+        anyhow::bail!("butuh {} baris data, ditemukan {}", size.pow(3), rows);
+    }
+    Ok(())
+}
+
+fn check_font(path: &Path) -> anyhow::Result<Vec<String>> {
+    let data = std::fs::read(path)
+        .with_context(|| format!("file {} tidak ditemukan", path.display()))?;
+    let m = FontMetrics::parse(&data)?;
+    let missing: String = (' '..='~').filter(|c| !m.has_glyph(*c)).collect();
+    if !missing.is_empty() {
+        anyhow::bail!("tidak punya glyph wajib: {}", missing);
+    }
+    let mut notes = Vec::new();
+    let extra: String = EXTRA_GLYPHS.chars().filter(|c| !m.has_glyph(*c)).collect();
+    if !extra.is_empty() {
+        notes.push(format!("tanpa glyph {extra}"));
+    }
+    if !EMOJI_SAMPLE.chars().any(|c| m.has_glyph(c)) {
+        notes.push("tidak mendukung emoji; emoji dari peserta akan dibuang".into());
+    }
+    Ok(notes)
+}
+
+// Muat semua template; manifest yang rusak dilaporkan tapi tidak menggagalkan yang lain.
+fn load_templates(root: &Path) -> (Vec<Template>, Vec<anyhow::Error>) {
+    let mut templates = Vec::new();
+    let mut errors = Vec::new();
+
+    let entries = match std::fs::read_dir(root) {
+        Ok(rd) => rd,
+        Err(e) => {
+            errors.push(anyhow::Error::new(e).context(format!("gagal membaca {}", root.display())));
+            return (templates, errors);
+        }
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    dirs.sort();
+
+    for dir in dirs {
+        if !retire_stale(&dir) {
+            continue;
+        }
+        match load_template(&dir) {
+            Ok(tpl) => templates.push(tpl),
+            Err(e) => errors.push(e),
+        }
+    }
+    (templates, errors)
+}
+
+// ================== HELPERS ==================
+fn ensure_dirs() -> std::io::Result<()> {
+    for d in ["uploads", "outputs", "templates"] {
+        std::fs::create_dir_all(d)?;
+    }
+    Ok(())
+}
+
+fn random_name(prefix: &str) -> String {
+    format!("{}{}", prefix, Uuid::new_v4().simple())
+}
+
+async fn ffprobe_duration(ffprobe: &str, path: &Path) -> anyhow::Result<f64> {
+    let out = Command::new(ffprobe)
+        .arg("-v")
+        .arg("error")
+        .arg("-show_entries")
+        .arg("format=duration")
+        .arg("-of")
+        .arg("default=noprint_wrappers=1:nokey=1")
+        .arg(path.as_os_str())
+        .stdout(Stdio::piped())
+        .output()
+        .await?;
+
+    if !out.status.success() {
+        anyhow::bail!("ffprobe failed: {}", String::from_utf8_lossy(&out.stderr));
+    }
+    let s = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    let dur = s.parse::<f64>().unwrap_or(0.0);
+    Ok(dur.max(0.0))
+}
+
+// Apakah file punya stream audio (klip kolase bisa saja tanpa suara).
+async fn ffprobe_has_audio(ffprobe: &str, path: &Path) -> bool {
+    let out = Command::new(ffprobe)
+        .args(["-v", "error", "-select_streams", "a"])
+        .args(["-show_entries", "stream=codec_type"])
+        .args(["-of", "default=noprint_wrappers=1:nokey=1"])
+        .arg(path.as_os_str())
+        .stdout(Stdio::piped())
+        .output()
+        .await;
+    matches!(out, Ok(o) if o.status.success() && !o.stdout.trim_ascii().is_empty())
+}
+
+async fn remove_files(paths: &[PathBuf]) {
+    for p in paths {
+        let _ = fs::remove_file(p).await;
+    }
+}
+
+// Tulis script filter graph lalu jalankan ffmpeg. File sementara dibersihkan pemanggil
+// lewat `cleanup_render`, baik berhasil maupun gagal.
+async fn run_ffmpeg(
+    fc_file: &Path,
+    script: &str,
+    args: &[String],
+) -> Result<std::process::Output, ActixError> {
+    fs::write(fc_file, script)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Command::new(&BINS.ffmpeg)
+        .args(args)
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped())
+        .output()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)
+}
+
+// Hapus file sementara satu render: input ternormalisasi, script graph, dan file teks.
+async fn cleanup_render(inputs: &[PathBuf], fc_file: &Path, ctx: &GraphCtx) {
+    let _ = fs::remove_file(fc_file).await;
+    remove_files(inputs).await;
+    for path in ctx.texts.values().flat_map(|t| &t.files) {
+        let _ = fs::remove_file(path).await;
+    }
+}
+
+fn to_args(a: &[&str]) -> Vec<String> {
+    a.iter().map(|s| s.to_string()).collect()
+}
+
+fn path_arg(p: &Path) -> String {
+    p.to_string_lossy().to_string()
+}
+
+// Versi teks perintah ffmpeg untuk ditampilkan di halaman hasil/debug.
+fn format_cmd(bin: &str, args: &[String]) -> String {
+    let mut s = bin.to_string();
+    for a in args {
+        s.push(' ');
+        if a.is_empty() || a.contains([' ', '"', '\'', ';']) {
+            s.push('"');
+            s.push_str(&a.replace('"', "\\\""));
+            s.push('"');
+        } else {
+            s.push_str(a);
+        }
+    }
+    s
+}
+
+fn html_escape(s: &str) -> String {
+    htmlescape::encode_minimal(s)
+}
+
+#[derive(Default)]
+struct PostFields {
+    source: Option<String>,
+    template: Option<String>,
+    title: Option<String>,
+    // satu per field `video`, urut sesuai kiriman (template kolase menerima beberapa)
+    uploads: Vec<Upload>,
+    // isian teks peserta dari field `f_<nama>`
+    texts: HashMap<String, String>,
+}
+
+struct Upload {
+    path: PathBuf,
+    ext: String,
+    mime: String,
+}
+
+const MAX_TEXT_CHARS: usize = 80;
+
+// Rapikan isian teks: buang karakter kontrol/baris baru, batasi panjang.
+fn clean_text_input(s: &str) -> String {
+    let one_line: String = s
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    one_line.trim().chars().take(MAX_TEXT_CHARS).collect()
+}
+
+// Batas isi field teks form (judul, key, isian peserta) dan manifest admin.
+const MAX_FIELD_BYTES: usize = 4 * 1024;
+const MAX_MANIFEST_BYTES: usize = 256 * 1024;
+
+fn too_large(limit: u64) -> ActixError {
+    actix_web::error::ErrorPayloadTooLarge(format!(
+        "Isi field melebihi batas {} KB",
+        limit / 1024
+    ))
+}
+
+// Tulis isi satu field multipart ke file; kembalikan jumlah byte. Berhenti (dan
+// hapus file setengah jadi) begitu ukurannya melewati `limit`.
+async fn write_field(field: &mut Field, path: &Path, limit: u64) -> Result<u64, ActixError> {
+    let mut f = File::create(path).await?;
+    let mut size: u64 = 0;
+    while let Some(chunk) = field.next().await {
+        let bytes = chunk?;
+        size += bytes.len() as u64;
+        if size > limit {
+            drop(f);
+            let _ = fs::remove_file(path).await;
+            return Err(too_large(limit));
+        }
+        f.write_all(&bytes).await?;
+    }
+    f.flush().await?;
+    Ok(size)
+}
+
+async fn read_text_field(field: &mut Field, limit: usize) -> Result<String, ActixError> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = field.next().await {
+        let data = chunk?;
+        if bytes.len() + data.len() > limit {
+            return Err(too_large(limit as u64));
+        }
+        bytes.extend_from_slice(&data);
+    }
+    Ok(String::from_utf8(bytes).unwrap_or_default())
+}
+
+fn lower_ext(filename: &str) -> String {
+    Path::new(filename)
+        .extension()
+        .and_then(OsStr::to_str)
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+async fn save_multipart(mut payload: Multipart) -> Result<PostFields, ActixError> {
+    let mut fields = PostFields::default();
+
+    while let Some(item) = payload.next().await {
+        let mut field = item?;
+        let cd = field.content_disposition().clone();
+        let name = cd.get_name().unwrap_or("").to_string();
+
+        if name == "video" || name == "photo" {
+            let what = if name == "photo" { "foto" } else { "video" };
+            if fields.uploads.len() >= MAX_COLLAGE_VIDEOS {
+                return Err(actix_web::error::ErrorBadRequest(format!("Terlalu banyak {what}")));
+            }
+            let filename = cd
+                .get_filename()
+                .map(sanitize)
+                .unwrap_or_else(|| format!("upload-{}.bin", Uuid::new_v4()));
+
+            let ext = lower_ext(&filename);
+
+            let tmp_path = PathBuf::from(format!("uploads/{}", random_name("raw_")));
+            let size = write_field(&mut field, &tmp_path, u64::MAX).await?;
+
+            if size < 1_000 {
+                return Err(actix_web::error::ErrorBadRequest(format!(
+                    "Upload {what} kosong/tidak valid"
+                )));
+            }
+
+            fields.uploads.push(Upload {
+                path: tmp_path,
+                ext,
+                mime: field
+                    .content_type()
+                    .map(|mime| mime.essence_str().to_string())
+                    .unwrap_or_else(|| "application/octet-stream".to_string()),
+            });
+        } else {
+            // text fields
+            let text = read_text_field(&mut field, MAX_FIELD_BYTES).await?;
+            match name.as_str() {
+                "source" => fields.source = Some(text),
+                "template" => fields.template = Some(text),
+                "title" => fields.title = Some(text),
+                other => {
+                    if let Some(key) = other.strip_prefix("f_") {
+                        let text = clean_text_input(&text);
+                        if !text.is_empty() {
+                            fields.texts.insert(key.to_string(), text);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(fields)
+}
+
+// ================== TEXT LAYOUT ==================
+// Ukuran font dan pemenggalan baris dihitung di server dari metrik font template,
+// bukan diserahkan ke drawtext, supaya hasilnya deterministik: teks + kotak + font
+// yang sama selalu menghasilkan ukuran dan baris yang sama.
+const ELLIPSIS: &str = "…";
+
+struct FontMetrics<'a> {
+    face: ttf_parser::Face<'a>,
+}
+
+impl<'a> FontMetrics<'a> {
+    fn parse(data: &'a [u8]) -> anyhow::Result<Self> {
+        let face = ttf_parser::Face::parse(data, 0)
+            .map_err(|e| anyhow::anyhow!("bukan font TTF/OTF yang valid ({e})"))?;
+        Ok(FontMetrics { face })
+    }
+
+    fn upem(&self) -> f64 {
+        self.face.units_per_em() as f64
+    }
+
+    // Lebar advance dalam satuan em.
+    fn advance_em(&self, c: char) -> f64 {
+        self.face
+            .glyph_index(c)
+            .or_else(|| self.face.glyph_index('?'))
+            .and_then(|g| self.face.glyph_hor_advance(g))
+            .map(|a| a as f64 / self.upem())
+            .unwrap_or(0.5)
+    }
+
+    fn ascent_em(&self) -> f64 {
+        self.face.ascender() as f64 / self.upem()
+    }
+
+    fn line_height_em(&self) -> f64 {
+        let f = &self.face;
+        (f.ascender() as f64 - f.descender() as f64 + f.line_gap() as f64) / self.upem()
+    }
+
+    fn has_glyph(&self, c: char) -> bool {
+        self.face.glyph_index(c).is_some()
+    }
+
+    // Buang karakter yang tidak ada di font (mis. emoji) supaya tidak tampil sebagai kotak.
+    fn supported(&self, text: &str) -> String {
+        let kept: String = text
+            .chars()
+            .filter(|c| c.is_whitespace() || self.has_glyph(*c))
+            .collect();
+        kept.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+struct TextLayout {
+    size: i32,
+    lines: Vec<String>,
+    line_height: f64,
+    ascent: f64,
+}
+
+// Lebar teks dalam piksel pada ukuran `size`, termasuk outline di kedua sisi.
+// Kerning diabaikan, jadi diberi margin 3% agar tidak mepet tepi kotak.
+fn text_width(m: &FontMetrics, text: &str, size: i32, stroke: i32) -> f64 {
+    let em: f64 = text.chars().map(|c| m.advance_em(c)).sum();
+    em * size as f64 * 1.03 + 2.0 * stroke as f64
+}
+
+// Bungkus kata secara greedy; kata yang lebih lebar dari kotak dipotong per karakter.
+fn wrap_words(m: &FontMetrics, text: &str, size: i32, stroke: i32, max_w: f64) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut cur = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if cur.is_empty() { word.to_string() } else { format!("{cur} {word}") };
+        if text_width(m, &candidate, size, stroke) <= max_w {
+            cur = candidate;
+            continue;
+        }
+        if !cur.is_empty() {
+            lines.push(std::mem::take(&mut cur));
+        }
+        for c in word.chars() {
+            let next = format!("{cur}{c}");
+            if !cur.is_empty() && text_width(m, &next, size, stroke) > max_w {
+                lines.push(std::mem::take(&mut cur));
+                cur.push(c);
+            } else {
+                cur = next;
+            }
+        }
+    }
+    if !cur.is_empty() {
+        lines.push(cur);
+    }
+    lines
+}
+
+// Potong dari belakang sampai teks + elipsis muat.
+fn ellipsize(m: &FontMetrics, text: &str, size: i32, stroke: i32, max_w: f64) -> String {
+    if text_width(m, text, size, stroke) <= max_w {
+        return text.to_string();
+    }
+    let ell = if m.has_glyph('…') { ELLIPSIS } else { "..." };
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let s = format!("{}{ell}", chars.iter().collect::<String>().trim_end());
+        if text_width(m, &s, size, stroke) <= max_w {
+            return s;
+        }
+    }
+    ell.to_string()
+}
+
+// Cari ukuran terbesar (size .. min_size) di mana teks muat dalam kotak, dengan
+// pemenggalan baris hingga max_lines. Kalau tetap tidak muat di min_size, baris
+// terakhir diberi elipsis.
+fn layout_text(m: &FontMetrics, tb: &TextBox, text: &str) -> TextLayout {
+    let (max_w, max_h) = (tb.w as f64, tb.h as f64);
+    let stroke = if tb.stroke.is_some() { tb.stroke_width } else { 0 };
+    let make = |size: i32, lines: Vec<String>| TextLayout {
+        size,
+        lines,
+        line_height: m.line_height_em() * size as f64,
+        ascent: m.ascent_em() * size as f64,
+    };
+
+    for size in (tb.min_size()..=tb.size).rev() {
+        let lh = m.line_height_em() * size as f64;
+        if text_width(m, text, size, stroke) <= max_w && lh <= max_h {
+            return make(size, vec![text.to_string()]);
+        }
+        if tb.max_lines > 1 {
+            let lines = wrap_words(m, text, size, stroke, max_w);
+            if lines.len() <= tb.max_lines && lines.len() as f64 * lh <= max_h {
+                return make(size, lines);
+            }
+        }
+    }
+
+    let size = tb.min_size();
+    let lh = m.line_height_em() * size as f64;
+    let fit_lines = ((max_h / lh).floor() as usize).clamp(1, tb.max_lines);
+    let mut lines = wrap_words(m, text, size, stroke, max_w);
+    if lines.len() > fit_lines {
+        let rest = lines.split_off(fit_lines - 1).join(" ");
+        lines.push(rest);
+    }
+    if let Some(last) = lines.last_mut() {
+        *last = ellipsize(m, last, size, stroke, max_w);
+    }
+    make(size, lines)
+}
+
+// Hasil layout yang sudah ditulis ke file sementara, satu file per baris.
+struct RenderedText {
+    layout: TextLayout,
+    files: Vec<PathBuf>,
+}
+
+// Data per-render yang dipakai graph selain template itu sendiri.
+#[derive(Default)]
+struct GraphCtx {
+    // indeks overlay teks -> baris teks peserta (dibaca drawtext via textfile=)
+    texts: HashMap<usize, RenderedText>,
+    // jendela tampil per indeks overlay (None = dilewati untuk render ini)
+    windows: Vec<Option<Window>>,
+    // durasi output dan durasi tiap video peserta (urut input)
+    duration: f64,
+    clips: Vec<f64>,
+    // input video peserta yang punya audio (dipakai untuk kolase, musik, dan bumper)
+    audio_inputs: Vec<usize>,
+    // bumper template: (durasi, punya audio)
+    intro: Option<(f64, bool)>,
+    outro: Option<(f64, bool)>,
+    // mode foto: satu frame, tanpa audio/musik/bumper
+    still: bool,
+    // input 0 berupa audio saja (ditampilkan lewat visualizer template)
+    audio_only: bool,
+}
+
+// Hasil build_filter_graph: isi script + stream yang di-map ke output.
+struct FilterGraph {
+    script: String,
+    video: String,
+    // None = output tanpa audio
+    audio: Option<String>,
+}
+
+// Kutip nilai opsi filter; isi di dalam '...' tidak diinterpretasi ffmpeg.
+fn filter_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "/").replace('\'', "'\\''"))
+}
+
+// Satu drawtext per baris. Baseline tiap baris diletakkan dari metrik font kita
+// (`ascent` milik drawtext dikurangkan) agar jarak antar baris konsisten.
+fn drawtext_filters(
+    tb: &TextBox,
+    font: &Path,
+    text: &RenderedText,
+    alpha: Option<&str>,
+    enable: &str,
+) -> String {
+    let layout = &text.layout;
+    let block_h = layout.line_height * layout.lines.len() as f64;
+    let top = tb.y as f64 + (tb.h as f64 - block_h) / 2.0;
+    text.files
+        .iter()
+        .enumerate()
+        .map(|(i, file)| {
+            let baseline = top + i as f64 * layout.line_height + layout.ascent;
+            let y = format!("{baseline:.1}-ascent");
+            drawtext_filter(tb, font, file, layout.size, &y, alpha, enable)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn drawtext_filter(
+    tb: &TextBox,
+    font: &Path,
+    text_file: &Path,
+    size: i32,
+    y: &str,
+    alpha: Option<&str>,
+    enable: &str,
+) -> String {
+    let x = match tb.align {
+        TextAlign::Left => format!("{}", tb.x),
+        TextAlign::Center => format!("{}+({}-text_w)/2", tb.x, tb.w),
+        TextAlign::Right => format!("{}+{}-text_w", tb.x, tb.w),
+    };
+    let font = filter_quote(&font.to_string_lossy());
+    let stroke = match &tb.stroke {
+        Some(c) if tb.stroke_width > 0 => format!(":borderw={}:bordercolor={}", tb.stroke_width, c),
+        _ => String::new(),
+    };
+    let alpha = alpha.map(|a| format!(":alpha={a}")).unwrap_or_default();
+    // expansion=none: teks peserta tidak boleh memicu %{...} milik drawtext
+    format!(
+        "drawtext=fontfile={font}:textfile={}:expansion=none:fontsize={}:fontcolor={}{stroke}{alpha}:x={x}:y={y}:enable={enable}",
+        filter_quote(&text_file.to_string_lossy()),
+        size,
+        tb.color,
+    )
+}
+
+// Ganti `w`/`h` (dan `overlay_w`/`overlay_h`) di ekspresi x/y overlay dengan ukuran
+// gambar saat ini; identifier lain (`main_w`, `W`, fungsi) dibiarkan.
+fn overlay_size_expr(expr: &str, w: Option<&str>, h: Option<&str>) -> String {
+    let mut out = String::new();
+    let mut rest = expr;
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            let (ident, tail) = rest.split_at(end);
+            match (ident, w, h) {
+                ("w" | "overlay_w", Some(w), _) => out.push_str(&format!("({w})")),
+                ("h" | "overlay_h", _, Some(h)) => out.push_str(&format!("({h})")),
+                _ => out.push_str(ident),
+            }
+            rest = tail;
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+// Build filter_complex graph; kembalikan graph + label output video terakhir.
+fn build_filter_graph(tpl: &Template, ctx: &GraphCtx) -> FilterGraph {
+    let mut chains: Vec<String> = Vec::new();
+    let (out_w, out_h) = (tpl.output.w(), tpl.output.h());
+
+    // scale-to-cover + crop, atau scale-to-fit + pad, ke kotak w x h
+    let cover = |w: i32, h: i32| -> String {
+        let ratio = w as f64 / h as f64;
+        format!(
+            "scale=if(gte(a\\,{ratio})\\,-2\\,{w}):if(gte(a\\,{ratio})\\,{h}\\,-2):flags=fast_bilinear,\
+             crop={w}:{h}:(iw-{w})/2:(ih-{h})/2,setsar=1"
+        )
+    };
+    let bg = tpl.visible_background();
+    // LUT dipasang setelah scale (lebih sedikit piksel) dan sebelum bar pad/overlay
+    let grade = |v: usize| tpl.lut.as_ref().map(|l| l.filters(v)).unwrap_or_default();
+    let key = tpl.chroma.as_ref().map(ChromaKey::filter).unwrap_or_default();
+    let fit = |w: i32, h: i32, v: usize| -> String {
+        if tpl.output.fill == FillMode::Crop {
+            format!("{}{}", cover(w, h), grade(v))
+        } else {
+            // bar pad dibuat tembus bila ada background di bawahnya
+            let bars = if bg.is_some() { ",format=rgba" } else { "" };
+            let color = if bg.is_some() { ":color=black@0" } else { "" };
+            format!(
+                "scale={w}:{h}:force_original_aspect_ratio=decrease:flags=fast_bilinear{}{bars},\
+                 pad={w}:{h}:(ow-iw)/2:(oh-ih)/2{color},setsar=1",
+                grade(v)
+            )
+        }
+    };
+
+    let slots = tpl.video_slots();
+    let videos = tpl.video_count();
+    // urutan input: video peserta, overlay gambar, mask slot, gambar background, musik,
+    // lalu bumper intro/outro
+    let mut mask_in = videos + tpl.overlays.iter().filter(|ol| ol.has_input()).count();
+    let bg_in = mask_in + slots.iter().filter(|s| s.mask_image().is_some()).count();
+    // kolase: klip yang lebih pendek menahan frame terakhir sampai akhir output
+    let hold = |v: usize| match ctx.clips.get(v) {
+        Some(d) if videos > 1 && ctx.duration - d > 0.05 => {
+            format!("tpad=stop_mode=clone:stop_duration={:.3},", ctx.duration - d)
+        }
+        _ => String::new(),
+    };
+
+    // background blur memakai video pertama, jadi stream itu di-split dulu
+    let blur_src = !ctx.audio_only && matches!(bg, Some(Background::Blur { .. }));
+    if blur_src {
+        chains.push(format!("[0:v]{}split=2[src0][bgsrc]", hold(0)));
+    }
+    let src = |v: usize| {
+        if v == 0 && blur_src {
+            "[src0]".to_string()
+        } else {
+            format!("[{v}:v]{}", hold(v))
+        }
+    };
+
+    match bg {
+        // upload audio tidak punya video untuk di-blur; pakai kanvas polos
+        None | Some(Background::Blur { .. }) if ctx.audio_only => chains.push(format!(
+            "color=c=black:s={out_w}x{out_h}:r={}[canvas]",
+            tpl.output.fps
+        )),
+        None if slots.is_empty() => {}
+        None => chains.push(format!(
+            "color=c=black:s={out_w}x{out_h}:r={}[canvas]",
+            tpl.output.fps
+        )),
+        Some(Background::Image { .. } | Background::Video { .. }) => {
+            chains.push(format!("[{bg_in}:v]{}[canvas]", cover(out_w, out_h)))
+        }
+        Some(Background::Blur { sigma, dim }) => {
+            // blur di resolusi 1/4 lalu di-upscale: hasil sama halusnya, jauh lebih ringan
+            let (sw, sh) = ((out_w / 8).max(1) * 2, (out_h / 8).max(1) * 2);
+            let darken = if *dim > 0.0 {
+                format!(",eq=brightness=-{dim:.2}")
+            } else {
+                String::new()
+            };
+            chains.push(format!(
+                "[bgsrc]{},gblur=sigma={:.1},scale={out_w}:{out_h}{darken}[canvas]",
+                cover(sw, sh),
+                sigma / 4.0
+            ));
+        }
+    }
+
+    if let Some(viz) = tpl.visualizer.as_ref().filter(|_| ctx.audio_only) {
+        chains.push(format!("[0:a]{}[viz]", viz.filter(tpl.output.fps)));
+        chains.push(format!(
+            "[canvas][viz]overlay=x={}:y={}:shortest=1,setsar=1[base]",
+            viz.x, viz.y
+        ));
+    } else if slots.is_empty() {
+        if bg.is_some() {
+            // mode pad di atas background: video di-fit tanpa bar lalu ditaruh di tengah;
+            // chroma key dengan fill crop tetap mengisi kanvas
+            let fg = if tpl.output.fill == FillMode::Crop {
+                format!("{}{}", cover(out_w, out_h), grade(0))
+            } else {
+                format!(
+                    "scale={out_w}:{out_h}:force_original_aspect_ratio=decrease:\
+                     flags=fast_bilinear{},setsar=1",
+                    grade(0)
+                )
+            };
+            chains.push(format!("{}{key}{fg}[fg]", src(0)));
+            chains.push("[canvas][fg]overlay=(W-w)/2:(H-h)/2:shortest=1,setsar=1[base]".into());
+        } else {
+            chains.push(format!("{}{}[base]", src(0), fit(out_w, out_h, 0)));
+        }
+    } else {
+        let mut below = String::from("canvas");
+        for (v, slot) in slots.iter().enumerate() {
+            let (w, h) = (slot.w, slot.h);
+            let (cx, cy) = ((w - 1) as f64 / 2.0, (h - 1) as f64 / 2.0);
+            // mask bawaan dibuat sekali (1 frame); alphamerge mengulang frame terakhir
+            let shape = match &slot.mask {
+                Some(SlotMask::Image(_)) => {
+                    mask_in += 1;
+                    Some(format!("[{}:v]scale={w}:{h},format=gray", mask_in - 1))
+                }
+                Some(SlotMask::Circle) => {
+                    // tepi dihaluskan ~1px
+                    let (rx, ry) = (w as f64 / 2.0, h as f64 / 2.0);
+                    Some(format!(
+                        "color=c=white:s={w}x{h}:r=1:d=1,format=gray,\
+                         geq=lum=255*clip((1-hypot((X-{cx})/{rx}\\,(Y-{cy})/{ry}))*{}+0.5\\,0\\,1)",
+                        rx.min(ry)
+                    ))
+                }
+                None if slot.radius > 0 => {
+                    let r = slot.radius;
+                    let (ix, iy) = (w as f64 / 2.0 - r as f64, h as f64 / 2.0 - r as f64);
+                    Some(format!(
+                        "color=c=white:s={w}x{h}:r=1:d=1,format=gray,\
+                         geq=lum=255*lte(hypot(max(0\\,abs(X-{cx})-{ix})\\,max(0\\,abs(Y-{cy})-{iy}))\\,{r})"
+                    ))
+                }
+                None => None,
+            };
+            if let Some(shape) = shape {
+                chains.push(format!("{shape}[m{v}]"));
+                chains.push(format!("{}{key}{},format=rgba[s{v}a]", src(v), fit(w, h, v)));
+                if key.is_empty() {
+                    chains.push(format!("[s{v}a][m{v}]alphamerge[s{v}]"));
+                } else {
+                    // alpha hasil chroma key dikalikan dengan mask, bukan diganti
+                    chains.push(format!("[s{v}a]split[s{v}c][s{v}k]"));
+                    chains.push(format!("[s{v}k]alphaextract[k{v}]"));
+                    chains.push(format!("[k{v}][m{v}]blend=all_mode=multiply[km{v}]"));
+                    chains.push(format!("[s{v}c][km{v}]alphamerge[s{v}]"));
+                }
+            } else {
+                chains.push(format!("{}{key}{}[s{v}]", src(v), fit(w, h, v)));
+            }
+            let next = if v + 1 == slots.len() { "base".to_string() } else { format!("c{v}") };
+            chains.push(format!(
+                "[{below}][s{v}]overlay=x={}:y={}:shortest=1,setsar=1[{next}]",
+                slot.x, slot.y
+            ));
+            below = next;
+        }
+    }
+
+    let mut prev = Cow::Borrowed("base");
+    let mut ov_index = 0usize;
+    let mut in_tag = videos - 1; // input overlay dimulai setelah video peserta
+
+    for (i, ol) in tpl.overlays.iter().enumerate() {
+        let Some(win) = ctx.windows.get(i).copied().flatten() else {
+            // input `-i` overlay tetap ada di command, jadi nomor input tetap dihitung
+            if ol.has_input() {
+                in_tag += 1;
+            }
+            continue;
+        };
+        let enable = format!("between(t\\,{:.3}\\,{:.3})", win.start, win.end);
+
+        let (sw, sh, x, y) = match &ol.typ {
+            // teks digambar langsung dengan drawtext, tanpa input overlay
+            OverlayType::Text(tb) => {
+                // kolom kosong -> tidak digambar
+                let (Some(text), Some(font)) = (ctx.texts.get(&i), tpl.fonts.get(&tb.font))
+                else {
+                    continue;
+                };
+                ov_index += 1;
+                chains.push(format!(
+                    "[{prev}]{}[v{ov_index}]",
+                    drawtext_filters(tb, font, text, ol.text_alpha(&win).as_deref(), &enable)
+                ));
+                prev = Cow::Owned(format!("v{}", ov_index));
+                continue;
+            }
+            OverlayType::Full => (out_w, out_h, "0".into(), "0".into()),
+            OverlayType::Band { h, x, y } => (
+                out_w,
+                h.unwrap_or(160),
+                x.clone().unwrap_or_else(|| "(main_w-w)/2".into()),
+                y.clone().unwrap_or_else(|| "main_h-h".into()),
+            ),
+            OverlayType::Logo { w, h, x, y } => (
+                w.unwrap_or(220),
+                h.unwrap_or(-1),
+                x.clone().unwrap_or_else(|| "main_w-w-24".into()),
+                y.clone().unwrap_or_else(|| "24".into()),
+            ),
+        };
+        in_tag += 1;
+        ov_index += 1;
+        let tag_ov = format!("ov{}", ov_index);
+
+        // Keyframe: x/y overlay dan ukuran scale dievaluasi per frame terhadap `t`;
+        // foto memakai posisi akhir supaya slide-in tidak tertangkap masih di luar kanvas.
+        let motion = |pick: &dyn Fn(&Keyframe) -> Option<String>| {
+            if ctx.still {
+                ol.settled(pick)
+            } else {
+                ol.motion(win.start, pick)
+            }
+        };
+        let x = motion(&|k| k.x.as_ref().map(KfValue::expr)).unwrap_or(x);
+        let y = motion(&|k| k.y.as_ref().map(KfValue::expr)).unwrap_or(y);
+        let (scale, x, y) = match motion(&|k| k.scale.map(|s| format!("{s:.3}"))) {
+            None => (format!("scale={sw}:{sh}:flags=fast_bilinear"), x, y),
+            Some(s) => {
+                // sisi -1 tetap mengikuti rasio aspek
+                let dim = |d: i32| {
+                    if d > 0 {
+                        format!("max(2\\,trunc({d}*{s}/2)*2)")
+                    } else {
+                        d.to_string()
+                    }
+                };
+                let scale = format!("scale=w={}:h={}", dim(sw), dim(sh));
+                if ctx.still {
+                    (format!("{scale}:flags=fast_bilinear"), x, y)
+                } else {
+                    // Ukuran berubah per frame, jadi hasilnya di-pad ke kotak tetap seukuran
+                    // keyframe terbesar: overlay selalu menerima frame berukuran sama. Gambar
+                    // diletakkan di tengah kotak, lalu x/y dihitung dari ukuran gambar saat
+                    // itu dan digeser setengah sisa kotak: posisinya sama dengan tanpa pad
+                    // (juga dengan mode foto) dan skala tumbuh dari titik jangkar x/y.
+                    let (bw, bh) = match ol.probed {
+                        Some((pw, ph)) if sw <= 0 && sh > 0 && ph > 0 => (sh * pw / ph, sh),
+                        Some((pw, ph)) if sh <= 0 && sw > 0 && pw > 0 => (sw, sw * ph / pw),
+                        _ => (sw, sh),
+                    };
+                    let max_s = ol.keyframes.iter().filter_map(|k| k.scale).fold(0.0, f64::max);
+                    let side = |d: i32, canvas: i32| {
+                        if d > 0 {
+                            ((d as f64 * max_s / 2.0).ceil() as i32 + 1) * 2
+                        } else {
+                            canvas
+                        }
+                    };
+                    let (box_w, box_h) = (side(bw, out_w), side(bh, out_h));
+                    // ukuran gambar saat ini (ekspresi waktu); sisi -1 dibulatkan seperti scale
+                    let (cur_w, cur_h) = match ol.probed {
+                        _ if sw > 0 && sh > 0 => (Some(dim(sw)), Some(dim(sh))),
+                        Some((pw, ph)) if sw > 0 && pw > 0 => {
+                            (Some(dim(sw)), Some(format!("round({}*{ph}/{pw})", dim(sw))))
+                        }
+                        Some((pw, ph)) if sh > 0 && ph > 0 => {
+                            (Some(format!("round({}*{pw}/{ph})", dim(sh))), Some(dim(sh)))
+                        }
+                        _ => (None, None),
+                    };
+                    let (cur_w, cur_h) = (cur_w.as_deref(), cur_h.as_deref());
+                    let place = |expr: String, cur: Option<&str>, side: i32| match cur {
+                        Some(c) => format!(
+                            "({})-trunc(({side}-{c})/2)",
+                            overlay_size_expr(&expr, cur_w, cur_h)
+                        ),
+                        None => expr,
+                    };
+                    let center = |cur: Option<&str>, axis: &str| {
+                        if cur.is_some() {
+                            format!("(o{axis}-i{axis})/2")
+                        } else {
+                            "0".into()
+                        }
+                    };
+                    (
+                        format!(
+                            "{scale}:eval=frame:flags=fast_bilinear,format=rgba,\
+                             pad=w={box_w}:h={box_h}:x={}:y={}:color=black@0:eval=frame",
+                            center(cur_w, "w"),
+                            center(cur_h, "h")
+                        ),
+                        place(x, cur_w, box_w),
+                        place(y, cur_h, box_h),
+                    )
+                }
+            }
+        };
+
+        // Overlay animasi: potong sepanjang jendela tampil lalu geser PTS ke `start`
+        // supaya frame pertamanya muncul tepat saat overlay diaktifkan. Setelah habis
+        // (mode once) video utama diteruskan apa adanya.
+        let (prep, sync) = if ol.is_animated() {
+            (
+                format!(
+                    "trim=duration={:.3},setpts=PTS-STARTPTS+{:.3}/TB,",
+                    win.end - win.start,
+                    win.start
+                ),
+                "eof_action=pass",
+            )
+        } else {
+            (String::new(), "shortest=1")
+        };
+
+        chains.push(format!(
+            "[{in_tag}:v]{prep}{scale}{}[{tag_ov}]",
+            ol.alpha_filters(&win)
+        ));
+        if ol.blend == BlendMode::Normal {
+            chains.push(format!(
+                "[{prev}][{tag_ov}]overlay={sync}:x={x}:y={y}:enable={enable}[v{ov_index}]"
+            ));
+        } else {
+            // Overlay ditaruh dulu di kanvas transparen seukuran output (posisi, keyframe
+            // dan jendela waktu tetap berlaku), lalu warnanya di-blend dengan video. Hasil
+            // blend diberi alpha lapisan itu dan dikomposit biasa, jadi area transparan
+            // dan opacity/fade tetap menyisakan video asli.
+            let n = ov_index;
+            chains.push(format!(
+                "color=c=black@0:s={out_w}x{out_h}:r={},format=rgba[cv{n}]",
+                tpl.output.fps
+            ));
+            chains.push(format!(
+                "[cv{n}][{tag_ov}]overlay=eof_action=pass:format=auto:x={x}:y={y}:enable={enable},\
+                 split[ly{n}][la{n}]"
+            ));
+            chains.push(format!("[la{n}]alphaextract[lm{n}]"));
+            chains.push(format!("[ly{n}]format=gbrp[lc{n}]"));
+            chains.push(format!("[{prev}]format=gbrp,split[bb{n}][bm{n}]"));
+            let (top, bottom) = if ol.blend.base_on_top() {
+                (format!("bm{n}"), format!("lc{n}"))
+            } else {
+                (format!("lc{n}"), format!("bm{n}"))
+            };
+            chains.push(format!(
+                "[{top}][{bottom}]blend=all_mode={}:shortest=1[mx{n}]",
+                ol.blend.filter_name()
+            ));
+            chains.push(format!("[mx{n}][lm{n}]alphamerge[ma{n}]"));
+            chains.push(format!("[bb{n}][ma{n}]overlay=shortest=1[v{n}]"));
+        }
+
+        prev = Cow::Owned(format!("v{}", ov_index));
+    }
+
+    if ctx.still {
+        return FilterGraph { script: chains.join(";"), video: prev.into_owned(), audio: None };
+    }
+
+    // audio: satu video apa adanya; kolase memakai slot pilihan atau mix semua yang
+    // bersuara, di-apad supaya -shortest mengikuti panjang video
+    let music = tpl.music.as_ref().filter(|m| m.active());
+    if tpl.collage.is_none() && music.is_none() && tpl.bumpers.is_none() {
+        let (script, video) = (chains.join(";"), prev.into_owned());
+        return FilterGraph { script, video, audio: Some("0:a?".into()) };
+    }
+    let pick = tpl.collage.as_ref().and_then(|c| c.audio_slot);
+    let inputs: Vec<usize> = ctx
+        .audio_inputs
+        .iter()
+        .copied()
+        .filter(|&i| pick.is_none_or(|p| p - 1 == i))
+        .collect();
+    let voice = match inputs.as_slice() {
+        [] => None,
+        [i] => Some(format!("[{i}:a]apad")),
+        many => {
+            let labels: String = many.iter().map(|i| format!("[{i}:a]")).collect();
+            Some(format!(
+                "{labels}amix=inputs={}:duration=longest:dropout_transition=0,apad",
+                many.len()
+            ))
+        }
+    };
+    // musik latar: input terakhir, setelah gambar background
+    let has_bg = tpl.visible_background().and_then(Background::file).is_some();
+    let music_in = bg_in + has_bg as usize;
+    match (music, voice) {
+        (None, None) => {}
+        (None, Some(v)) => chains.push(format!("{v}[aout]")),
+        (Some(m), voice) => {
+            let bgm = m.chain(music_in, ctx.duration);
+            match voice.filter(|_| m.mode == MusicMode::Mix) {
+                None => chains.push(format!("{bgm}[aout]")),
+                Some(v) => {
+                    chains.push(format!(
+                        "{v},aformat=sample_rates=48000:channel_layouts=stereo,asplit=2[vo][vs]"
+                    ));
+                    if m.duck {
+                        chains.push(format!("{bgm}[bgm]"));
+                        chains.push(
+                            "[bgm][vs]sidechaincompress=threshold=0.03:ratio=8:\
+                             attack=20:release=400[duck]"
+                                .into(),
+                        );
+                    } else {
+                        chains.push(format!("{bgm}[duck]"));
+                        chains.push("[vs]anullsink".into());
+                    }
+                    // musik sudah dipotong ke durasi output, jadi ia yang menentukan panjang mix
+                    chains.push(
+                        "[duck][vo]amix=inputs=2:duration=first:dropout_transition=0:\
+                         normalize=0[aout]"
+                            .into(),
+                    );
+                }
+            }
+        }
+    }
+    let audio = (music.is_some() || !inputs.is_empty()).then(|| "[aout]".to_string());
+    let Some(bumpers) = &tpl.bumpers else {
+        return FilterGraph { script: chains.join(";"), video: prev.into_owned(), audio };
+    };
+
+    // bumper: semua segmen dinormalisasi ke profil output (ukuran, fps, audio stereo
+    // 48k) lalu disambung intro + komposit peserta + outro
+    let vnorm = format!("fps={},format=yuv420p,settb=AVTB", tpl.output.fps);
+    let anorm = "aformat=sample_rates=48000:channel_layouts=stereo";
+    let silence = |d: f64| format!("anullsrc=r=48000:cl=stereo,atrim=duration={d:.3}");
+    let bumper = |tag: &str, input: usize, (d, has_audio): (f64, bool)| {
+        let a = if has_audio {
+            format!("[{input}:a]{anorm},apad,atrim=duration={d:.3}")
+        } else {
+            silence(d)
+        };
+        [
+            format!("[{input}:v]{},{vnorm},trim=duration={d:.3}[{tag}v]", cover(out_w, out_h)),
+            format!("{a}[{tag}a]"),
+        ]
+    };
+    let mut bump_in = music_in + music.is_some() as usize;
+    let mut segs = Vec::new();
+    if let Some(clip) = ctx.intro {
+        chains.extend(bumper("intro", bump_in, clip));
+        segs.push(("intro", clip.0));
+        bump_in += 1;
+    }
+    // bagian peserta dipotong di sini (bukan lewat -t) supaya bumper tidak ikut terpotong
+    let dur = ctx.duration;
+    chains.push(format!("[{prev}]trim=duration={dur:.3},setpts=PTS-STARTPTS,{vnorm}[mainv]"));
+    let main_audio = match &audio {
+        Some(a) => format!("{a}atrim=duration={dur:.3},asetpts=PTS-STARTPTS,{anorm}"),
+        None => silence(dur),
+    };
+    chains.push(format!("{main_audio}[maina]"));
+    segs.push(("main", dur));
+    if let Some(clip) = ctx.outro {
+        chains.extend(bumper("outro", bump_in, clip));
+        segs.push(("outro", clip.0));
+    }
+
+    match &bumpers.transition {
+        None => {
+            let labels: String = segs.iter().map(|(t, _)| format!("[{t}v][{t}a]")).collect();
+            chains.push(format!("{labels}concat=n={}:v=1:a=1[outv][outa]", segs.len()));
+        }
+        Some(tr) => {
+            // transisi tidak boleh melebihi separuh segmen terpendek
+            let td = segs.iter().map(|s| s.1 / 2.0).fold(bumpers.transition_duration, f64::min);
+            let (first, mut len) = segs[0];
+            let (mut v, mut a) = (format!("{first}v"), format!("{first}a"));
+            for (i, &(t, d)) in segs.iter().enumerate().skip(1) {
+                let (nv, na) = if i + 1 == segs.len() {
+                    ("outv".to_string(), "outa".to_string())
+                } else {
+                    (format!("xv{i}"), format!("xa{i}"))
+                };
+                chains.push(format!(
+                    "[{v}][{t}v]xfade=transition={tr}:duration={td:.3}:offset={:.3}[{nv}]",
+                    len - td
+                ));
+                chains.push(format!("[{a}][{t}a]acrossfade=d={td:.3}[{na}]"));
+                (v, a, len) = (nv, na, len + d - td);
+            }
+        }
+    }
+
+    FilterGraph { script: chains.join(";"), video: "outv".into(), audio: Some("[outa]".into()) }
+}
+
+// ================== HTML ==================
+static HOME_JS: &str = r#"
+(function(){
+  const form = document.getElementById('twb-form');
+  const srcRadios = form.querySelectorAll('input[name="source"]');
+  const uploadPane = document.getElementById('upload-pane');
+  const recordPane = document.getElementById('record-pane');
+  const photoPane = document.getElementById('photo-pane');
+  const videoInput = form.querySelector('input[name="video"]');
+  const photoInput = form.querySelector('input[name="photo"]');
+
+  const cam = document.getElementById('cam');
+  const playback = document.getElementById('playback');
+  const btnOpen = document.getElementById('btnOpen');
+  const btnRec  = document.getElementById('btnRec');
+  const btnStop = document.getElementById('btnStop');
+  const timerEl = document.getElementById('timer');
+
+  const btnSubmit = document.getElementById('btnSubmit');
+  const waitNote  = document.getElementById('waitNote');
+
+  let stream = null, mr = null, chunks = [], recordedBlob = null, recTimer = null, startTs = 0;
+  const MAX_MS = 30000;
+
+  function setBusy(b){
+    btnSubmit.disabled = b;
+    btnSubmit.textContent = b ? 'Memproses…' : 'Generate';
+    waitNote.style.display = b ? 'block' : 'none';
+  }
+  function fmt(ms){
+    const s = Math.floor(ms/1000);
+    const m = String(Math.floor(s/60)).padStart(2,'0');
+    const ss = String(s%60).padStart(2,'0');
+    return m+':'+ss;
+  }
+  function startTimer(){
+    startTs = Date.now();
+    stopTimer();
+    recTimer = setInterval(()=>{ timerEl.textContent = fmt(Date.now()-startTs); }, 200);
+  }
+  function stopTimer(){
+    if (recTimer) { clearInterval(recTimer); recTimer = null; }
+  }
+  function stopStream(){
+    if (stream){
+      stream.getTracks().forEach(t=>t.stop());
+      stream = null;
+      cam.srcObject = null;
+    }
+    btnRec.disabled = true;
+    btnStop.disabled = true;
+  }
+  function switchPane(){
+    const v = form.source.value;
+    uploadPane.hidden = v !== 'upload';
+    recordPane.hidden = v !== 'record';
+    photoPane.hidden = v !== 'photo';
+    // input file di pane tersembunyi tidak ikut dikirim
+    videoInput.disabled = v !== 'upload';
+    photoInput.disabled = v !== 'photo';
+    if (v !== 'record') {
+      stopStream();
+      playback.hidden = true;
+      recordedBlob = null;
+      timerEl.textContent = '00:00';
+    }
+  }
+  srcRadios.forEach(r=>r.addEventListener('change', switchPane));
+  switchPane();
+
+  // ===== Kolom teks sesuai template =====
+  function switchFields(){
+    form.querySelectorAll('.tpl-fields').forEach(div=>{
+      const on = div.dataset.template === form.template.value;
+      div.hidden = !on;
+      div.querySelectorAll('input').forEach(i=>{ i.disabled = !on; });
+    });
+  }
+  form.template.addEventListener('change', switchFields);
+  switchFields();
+
+  // ===== Template kolase: beberapa video/foto sekaligus =====
+  const videoHint = document.getElementById('video-count');
+  function videoCount(){
+    const opt = form.template.selectedOptions[0];
+    return opt ? parseInt(opt.dataset.videos || '1', 10) : 1;
+  }
+  function switchVideos(){
+    const n = videoCount();
+    videoInput.multiple = n > 1;
+    photoInput.multiple = n > 1;
+    videoHint.hidden = n <= 1;
+    videoHint.textContent = 'Template ini kolase: pilih ' + n + ' file sekaligus (urutan = urutan slot).';
+  }
+  form.template.addEventListener('change', switchVideos);
+  switchVideos();
+
+  // ===== Kamera =====
+  btnOpen.addEventListener('click', async ()=>{
+    try {
+      stream = await navigator.mediaDevices.getUserMedia({
+        video: { facingMode: 'user', width: {ideal: 720}, height: {ideal: 1280} },
+        audio: true
+      });
+      cam.srcObject = stream;
+      btnRec.disabled = false;
+    } catch (e){
+      alert('Tidak bisa mengakses kamera/mikrofon: ' + (e.message||e));
+    }
+  });
+
+  btnRec.addEventListener('click', ()=>{
+    if (!stream) return;
+    if (!('MediaRecorder' in window)) {
+      alert('Browser Anda belum mendukung MediaRecorder. Gunakan Chrome/Edge/Firefox terbaru, atau upload file.');
+      return;
+    }
+    // Prefer MP4 (Safari), fallback WEBM
+    let mime = null;
+    if (MediaRecorder.isTypeSupported('video/mp4;codecs=avc1')) mime = 'video/mp4;codecs=avc1';
+    else if (MediaRecorder.isTypeSupported('video/mp4'))       mime = 'video/mp4';
+    else if (MediaRecorder.isTypeSupported('video/webm;codecs=vp9')) mime = 'video/webm;codecs=vp9';
+    else if (MediaRecorder.isTypeSupported('video/webm;codecs=vp8')) mime = 'video/webm;codecs=vp8';
+    else mime = 'video/webm';
+
+    chunks = [];
+    recordedBlob = null;
+    mr = new MediaRecorder(stream, { mimeType: mime, videoBitsPerSecond: 2_500_000 });
+    mr.ondataavailable = e => { if (e.data && e.data.size) chunks.push(e.data); };
+    mr.onstop = ()=>{
+      stopTimer();
+      recordedBlob = new Blob(chunks, { type: mr.mimeType || 'video/webm' });
+      chunks = [];
+      playback.src = URL.createObjectURL(recordedBlob);
+      playback.hidden = false;
+      btnRec.disabled = false;
+      btnStop.disabled = true;
+    };
+    mr.start(250);
+    btnRec.disabled = true;
+    btnStop.disabled = false;
+    startTimer();
+    setTimeout(()=>{ if (mr && mr.state === 'recording') mr.stop(); }, MAX_MS);
+  });
+
+  btnStop.addEventListener('click', ()=>{
+    if (mr && mr.state === 'recording') mr.stop();
+  });
+
+  // ===== Submit: Upload biasa (MP4) / foto =====
+  form.addEventListener('submit', (e)=>{
+    if (form.source.value === 'record') return; // record ditangani handler di bawah
+    const files = form.source.value === 'photo' ? photoInput.files : videoInput.files;
+    if (videoCount() > 1 && files.length !== videoCount()) {
+      e.preventDefault();
+      alert('Template ini butuh ' + videoCount() + ' file.');
+      return;
+    }
+    setBusy(true); // cegah double submit
   });
 
   // ===== Submit: Rekaman (kirim blob via fetch) =====
@@ -419,6 +3239,7 @@ static HOME_JS: &str = r#"
     if (form.source.value !== 'record') return;
     e.preventDefault();
     if (!recordedBlob) { alert('Silakan rekam dulu videonya.'); return; }
+    if (videoCount() > 1) { alert('Template kolase hanya bisa lewat upload.'); return; }
 
     try {
       setBusy(true);
@@ -428,6 +3249,7 @@ static HOME_JS: &str = r#"
       fd.append('template', form.template.value);
       fd.append('title', form.title.value);
       fd.append('source', 'record');
+      form.querySelectorAll('.tpl-fields input:not([disabled])').forEach(i=>fd.append(i.name, i.value));
 
       const res  = await fetch(form.action || location.href, { method:'POST', body:fd });
       const html = await res.text();
@@ -446,13 +3268,35 @@ fn render_home(warn: Option<&str>) -> String {
         .unwrap_or_default();
 
     let mut opts = String::new();
-    for t in TEMPLATES.iter() {
+    let mut text_fields = String::new();
+    for t in templates().iter().filter(|t| t.is_usable()) {
         opts.push_str(&format!(
-            "<option value=\"{}\">{} ({})</option>",
-            html_escape(t.key),
-            html_escape(t.title),
-            html_escape(t.key)
+            "<option value=\"{}\" data-videos=\"{}\">{} ({})</option>",
+            html_escape(&t.key),
+            t.video_count(),
+            html_escape(&t.title),
+            html_escape(&t.key)
+        ));
+        // kolom teks per template; JS hanya mengaktifkan milik template terpilih
+        let fields = t.text_fields();
+        if fields.is_empty() {
+            continue;
+        }
+        text_fields.push_str(&format!(
+            "<div class=\"tpl-fields\" data-template=\"{}\" hidden>",
+            html_escape(&t.key)
         ));
+        for tb in fields {
+            text_fields.push_str(&format!(
+                "<label>{}{}</label><input type=\"text\" name=\"f_{}\" maxlength=\"{}\"{} disabled>",
+                html_escape(tb.label()),
+                if tb.required { "" } else { " (opsional)" },
+                html_escape(&tb.field),
+                MAX_TEXT_CHARS,
+                if tb.required { " required" } else { "" }
+            ));
+        }
+        text_fields.push_str("</div>");
     }
 
     format!(
@@ -471,19 +3315,26 @@ fn render_home(warn: Option<&str>) -> String {
 </head><body>
   <h1>Vibbon Generator (Rust)</h1>
   {warn_html}
-  <p class="hint">Pilih sumber video: upload berkas atau rekam dari kamera. Maks 30 detik.</p>
+  <p class="hint">Pilih sumber video: upload berkas atau rekam dari kamera (maks 30 detik), atau upload foto untuk twibbon gambar.</p>
 
   <form id="twb-form" class="card" method="post" enctype="multipart/form-data" action="/">
     <fieldset style="border:0;padding:0;margin:0 0 12px">
       <legend style="font-weight:700;margin-bottom:6px">Sumber Video</legend>
       <label style="margin-right:12px"><input type="radio" name="source" value="upload" checked> Upload</label>
-      <label><input type="radio" name="source" value="record"> Rekam kamera</label>
+      <label style="margin-right:12px"><input type="radio" name="source" value="record"> Rekam kamera</label>
+      <label><input type="radio" name="source" value="photo"> Foto (hasil gambar)</label>
     </fieldset>
 
     <div id="upload-pane">
-      <label>Video (MP4/WEBM)</label>
-      <input type="file" name="video" accept="video/mp4,video/webm,video/*">
-      <p class="hint">Format disarankan: MP4 (h.264+aac) atau WEBM (vp8/9+opus).</p>
+      <label>Video (MP4/WEBM), foto, atau audio</label>
+      <input type="file" name="video" accept="video/mp4,video/webm,video/*,image/jpeg,image/png,image/heic,image/heif,image/webp,audio/mpeg,audio/mp4,audio/ogg">
+      <p class="hint">Format disarankan: MP4 (h.264+aac) atau WEBM (vp8/9+opus). Foto dijadikan video dengan efek zoom pelan; audio (MP3/M4A/OGG) ditampilkan sebagai gelombang suara bila template mendukung.</p>
+    </div>
+
+    <div id="photo-pane" hidden>
+      <label>Foto (JPEG/PNG/HEIC/WebP)</label>
+      <input type="file" name="photo" accept="image/jpeg,image/png,image/heic,image/heif,image/webp" disabled>
+      <p class="hint">Hasilnya gambar berbingkai template (PNG/JPEG), bukan video.</p>
     </div>
 
     <div id="record-pane" hidden>
@@ -499,11 +3350,13 @@ fn render_home(warn: Option<&str>) -> String {
         <p class="hint">Rekaman otomatis berhenti di 30 detik.</p>
       </div>
     </div>
+    <p class="hint" id="video-count" hidden></p>
 
     <label style="margin-top:12px">Pilih Template</label>
     <select name="template" required>
       {opts}
     </select>
+    {text_fields}
 
     <label>Judul Output (opsional)</label>
     <input type="text" name="title" placeholder="mis. video-twibbon">
@@ -516,11 +3369,19 @@ fn render_home(warn: Option<&str>) -> String {
 </body></html>"#,
         warn_html = warn_html,
         opts = opts,
+        text_fields = text_fields,
         js = HOME_JS
     )
 }
 
-fn render_result_page(title: &str, out_path: &str, full_cmd: &str) -> String {
+// Halaman hasil render; `photo` = hasil berupa gambar (mode foto), selain itu video.
+fn render_result_page(title: &str, out_path: &str, full_cmd: &str, photo: bool) -> String {
+    let out = html_escape(out_path);
+    let (kind, media) = if photo {
+        ("Foto", format!(r#"<img src="{out}" alt="Hasil twibbon">"#))
+    } else {
+        ("Video", format!(r#"<video controls src="{out}"></video>"#))
+    };
     format!(
         r#"<!doctype html>
 <html lang="id"><head>
@@ -530,23 +3391,22 @@ fn render_result_page(title: &str, out_path: &str, full_cmd: &str) -> String {
     body{{font-family:system-ui,-apple-system,Segoe UI,Roboto,Arial;color:#222;padding:24px;max-width:900px;margin:auto}}
     .card{{border:1px solid #ddd;border-radius:12px;padding:18px;margin:12px 0;background:#fff}}
     video{{width:360px;max-height:640px;border-radius:12px;border:1px solid #ddd}}
+    img{{max-width:360px;max-height:640px;border-radius:12px;border:1px solid #ddd;display:block}}
     a.btn{{display:inline-block;margin-top:12px;padding:12px 18px;background:#111;color:#fff;text-decoration:none;border-radius:10px;font-weight:700}}
     .mono{{font-family:ui-monospace,SFMono-Regular,Menlo,Consolas,monospace;font-size:.9em;color:#333;background:#f8f8f8;border-radius:8px;padding:10px;white-space:pre-wrap}}
   </style>
 </head><body>
-  <h1>✅ Video berhasil dibuat</h1>
+  <h1>✅ {kind} berhasil dibuat</h1>
   <div class="card">
-    <video controls src="{}"></video>
+    {media}
     <div>
-      <a class="btn" href="{}" download>Download Video</a>
+      <a class="btn" href="{out}" download>Download {kind}</a>
       <a class="btn" href="/">Buat Lagi</a>
     </div>
     <p class="mono">{}</p>
   </div>
 </body></html>"#,
         html_escape(title),
-        html_escape(out_path),
-        html_escape(out_path),
         html_escape(full_cmd)
     )
 }
@@ -568,45 +3428,71 @@ async fn home() -> impl Responder {
     HttpResponse::Ok().content_type("text/html; charset=utf-8").body(html)
 }
 
-#[derive(Deserialize)]
-struct Q {}
-
-#[post("/")]
-async fn process_upload(payload: Multipart) -> Result<impl Responder, ActixError> {
-    ensure_dirs().map_err(actix_web::error::ErrorInternalServerError)?;
-    let fields = save_multipart(payload).await?;
-
-    let source = fields.source.unwrap_or_else(|| "upload".into());
-    let template_key = fields
-        .template
-        .ok_or_else(|| actix_web::error::ErrorBadRequest("Template wajib diisi"))?;
-    let title_in = fields.title.unwrap_or_default();
-    let title = if title_in.trim().is_empty() {
-        let ts = humantime::format_rfc3339(SystemTime::now()).to_string();
-        format!("twibbon-{}-{}", ts.replace([':', 'T', 'Z', '.'], ""), &random_name(""))
-    } else {
-        sanitize(&title_in)
-    };
-
-    let tpl = TEMPLATES
-        .iter()
-        .find(|t| t.key == template_key)
-        .ok_or_else(|| actix_web::error::ErrorBadRequest("Template tidak dikenali"))?
-        .clone();
-
-    let upload_path = fields
-        .upload_path
-        .ok_or_else(|| actix_web::error::ErrorBadRequest("Upload video gagal"))?;
-    let ext = fields.upload_ext.unwrap_or_default();
-    let mime = fields.upload_mime.unwrap_or_default();
+// Foto di kampanye video: zoom pelan sambil bergeser (zoompan) sepanjang durasi
+// template, seukuran kotak video tujuannya. Hasilnya MP4 tanpa audio.
+async fn photo_to_video(
+    photo: &Path,
+    tpl: &Template,
+    (w, h): (i32, i32),
+) -> Result<PathBuf, ActixError> {
+    let fps = tpl.output.fps;
+    let secs = tpl.photo.duration.unwrap_or_else(|| tpl.duration());
+    let frames = ((secs * fps as f64).round() as i64).max(1);
+    // di-upscale 2x dulu supaya gerakan zoompan tidak patah-patah
+    let vf = format!(
+        "scale={sw}:{sh}:force_original_aspect_ratio=increase,crop={sw}:{sh},\
+         zoompan=z='1+{:.4}*on/{frames}':x='(iw-iw/zoom)*(0.5+0.25*on/{frames})':\
+         y='(ih-ih/zoom)/2':d={frames}:s={w}x{h}:fps={fps},setsar=1,format=yuv420p",
+        tpl.photo.zoom - 1.0,
+        sw = w * 2,
+        sh = h * 2
+    );
+    let input_path = PathBuf::from(format!("uploads/{}.mp4", random_name("vid_")));
+    let out = Command::new(&BINS.ffmpeg)
+        .arg("-y")
+        .arg("-i")
+        .arg(photo)
+        .args(["-vf", &vf, "-frames:v", &frames.to_string()])
+        .args(["-c:v", "libx264", "-preset", PRESET, "-crf", &CRF.to_string()])
+        .args(["-an", "-movflags", "+faststart"])
+        .arg(&input_path)
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped())
+        .output()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let _ = fs::remove_file(photo).await;
+    if !out.status.success() || !input_path.exists() {
+        let _ = fs::remove_file(&input_path).await;
+        let errlog = String::from_utf8_lossy(&out.stderr);
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "Gagal membuat video dari foto.\n{}",
+            errlog
+        )));
+    }
+    Ok(input_path)
+}
 
-    // --- Normalisasi/Validasi ke MP4 (hasilkan input_path) ---
-    let input_path: PathBuf = if source == "record" {
+// Validasi satu upload lalu hasilkan MP4 di uploads/ (rekaman WEBM dikonversi dulu,
+// foto di sumber upload dijadikan klip Ken Burns; foto di mode foto dan audio disimpan
+// apa adanya). `index` = urutan video, untuk ukuran slot tujuan. File mentah selalu
+// dibersihkan.
+async fn normalize_upload(
+    source: &str,
+    upload: Upload,
+    tpl: &Template,
+    index: usize,
+) -> Result<PathBuf, ActixError> {
+    let Upload { path: upload_path, ext, mime } = upload;
+    let is_photo = PHOTO_EXTS.contains(&ext.as_str())
+        && (mime.starts_with("image/") || mime == "application/octet-stream");
+    if source == "record" {
         if !["webm", "mp4"].contains(&ext.as_str())
             || !(mime.contains("video/webm")
                 || mime.contains("video/mp4")
                 || mime == "application/octet-stream")
         {
+            let _ = fs::remove_file(&upload_path).await;
             return Err(actix_web::error::ErrorBadRequest("Rekaman harus WEBM/MP4."));
         }
         let input_path = PathBuf::from(format!("uploads/{}.mp4", random_name("vid_")));
@@ -621,116 +3507,304 @@ async fn process_upload(payload: Multipart) -> Result<impl Responder, ActixError
             .stderr(Stdio::piped())
             .stdout(Stdio::piped());
 
-        let out = cmd
-            .output()
-            .await
-            .map_err(actix_web::error::ErrorInternalServerError)?;
-        // bersihkan raw
-        let _ = fs::remove_file(&upload_path).await;
-        if !out.status.success()
-            || !input_path.exists()
-            || input_path.metadata().map(|m| m.len()).unwrap_or(0) < 1000
-        {
-            let errlog = String::from_utf8_lossy(&out.stderr);
-            return Err(actix_web::error::ErrorBadRequest(format!(
-                "Gagal konversi rekaman ke MP4.\n{}",
-                errlog
-            )));
-        }
-        input_path
+        let out = cmd
+            .output()
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        // bersihkan raw
+        let _ = fs::remove_file(&upload_path).await;
+        if !out.status.success()
+            || !input_path.exists()
+            || input_path.metadata().map(|m| m.len()).unwrap_or(0) < 1000
+        {
+            let errlog = String::from_utf8_lossy(&out.stderr);
+            return Err(actix_web::error::ErrorBadRequest(format!(
+                "Gagal konversi rekaman ke MP4.\n{}",
+                errlog
+            )));
+        }
+        Ok(input_path)
+    } else if source == "photo" {
+        // HEIC/WebP didekode langsung oleh ffmpeg
+        if !is_photo {
+            let _ = fs::remove_file(&upload_path).await;
+            return Err(actix_web::error::ErrorBadRequest("Foto harus JPEG/PNG/HEIC/WebP."));
+        }
+        let input_path = PathBuf::from(format!("uploads/{}.{ext}", random_name("img_")));
+        fs::rename(&upload_path, &input_path)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        Ok(input_path)
+    } else if AUDIO_UPLOAD_EXTS.contains(&ext.as_str())
+        && (mime.starts_with("audio/") || mime == "application/octet-stream")
+    {
+        if tpl.visualizer.is_none() {
+            let _ = fs::remove_file(&upload_path).await;
+            return Err(actix_web::error::ErrorBadRequest(
+                "Template ini tidak menerima upload audio.",
+            ));
+        }
+        let input_path = PathBuf::from(format!("uploads/{}.{ext}", random_name("aud_")));
+        fs::rename(&upload_path, &input_path)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        Ok(input_path)
+    } else if is_photo {
+        let target = tpl
+            .video_slots()
+            .get(index)
+            .map_or((tpl.output.w(), tpl.output.h()), |s| (s.w, s.h));
+        photo_to_video(&upload_path, tpl, target).await
+    } else {
+        // Upload biasa: MP4, foto (dijadikan video), atau audio (template ber-visualizer)
+        if ext.as_str() != "mp4" || !(mime.contains("video/mp4") || mime == "application/octet-stream") {
+            let _ = fs::remove_file(&upload_path).await;
+            let accepted = if tpl.visualizer.is_some() {
+                "File harus MP4, foto (JPEG/PNG/HEIC/WebP), atau audio (MP3/M4A/OGG)."
+            } else {
+                "File harus MP4 atau foto (JPEG/PNG/HEIC/WebP)."
+            };
+            return Err(actix_web::error::ErrorBadRequest(accepted));
+        }
+        let input_path = PathBuf::from(format!("uploads/{}.mp4", random_name("vid_")));
+        fs::rename(&upload_path, &input_path)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        Ok(input_path)
+    }
+}
+
+fn output_file_name(title: &str, ext: &str) -> String {
+    let stem: String = sanitize(title)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    format!("{stem}.{ext}")
+}
+
+// Input `-i` bersama mode video dan foto, urut sesuai build_filter_graph: file
+// peserta, overlay gambar, mask slot, lalu background.
+fn visual_input_args(tpl: &Template, inputs: &[PathBuf]) -> Vec<String> {
+    let mut args = Vec::new();
+    for input in inputs {
+        args.extend(["-i".into(), path_arg(input)]);
+    }
+    for ol in tpl.overlays.iter().filter(|ol| ol.has_input()) {
+        args.extend(ol.input_args(tpl.output.fps));
+    }
+    for mask in tpl.video_slots().into_iter().filter_map(VideoSlot::mask_image) {
+        args.extend(["-i".into(), path_arg(mask)]);
+    }
+    if let Some(bg) = tpl.visible_background() {
+        args.extend(bg.input_args(tpl.output.fps));
+    }
+    args
+}
+
+// Layout teks peserta lalu tulis per baris ke file sementara (dibaca drawtext).
+// Bila gagal, file teks yang sudah ditulis ikut dihapus.
+async fn render_texts(
+    tpl: &Template,
+    texts: &HashMap<String, String>,
+    ctx: &mut GraphCtx,
+) -> Result<(), ActixError> {
+    let result = write_texts(tpl, texts, ctx).await;
+    if result.is_err() {
+        for path in ctx.texts.drain().flat_map(|(_, t)| t.files) {
+            let _ = fs::remove_file(path).await;
+        }
+    }
+    result
+}
+
+async fn write_texts(
+    tpl: &Template,
+    texts: &HashMap<String, String>,
+    ctx: &mut GraphCtx,
+) -> Result<(), ActixError> {
+    for (i, ol) in tpl.overlays.iter().enumerate() {
+        let OverlayType::Text(tb) = &ol.typ else { continue };
+        let Some(text) = texts.get(&tb.field) else { continue };
+        let font = tpl
+            .fonts
+            .get(&tb.font)
+            .ok_or_else(|| actix_web::error::ErrorInternalServerError("Font template hilang"))?;
+        let font_data = fs::read(font)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        let metrics =
+            FontMetrics::parse(&font_data).map_err(actix_web::error::ErrorInternalServerError)?;
+        let text = metrics.supported(text);
+        if text.is_empty() {
+            continue;
+        }
+        let layout = layout_text(&metrics, tb, &text);
+        // dicatat di ctx sebelum ditulis supaya file yang sempat dibuat ikut dibersihkan
+        let RenderedText { layout, files } =
+            ctx.texts.entry(i).or_insert(RenderedText { layout, files: Vec::new() });
+        for line in &layout.lines {
+            let path = PathBuf::from(format!("uploads/{}.txt", random_name("txt_")));
+            files.push(path.clone());
+            fs::write(&path, line)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+        }
+    }
+    Ok(())
+}
+
+#[post("/")]
+async fn process_upload(payload: Multipart) -> Result<impl Responder, ActixError> {
+    ensure_dirs().map_err(actix_web::error::ErrorInternalServerError)?;
+    let fields = save_multipart(payload).await?;
+
+    let source = fields.source.unwrap_or_else(|| "upload".into());
+    let template_key = fields
+        .template
+        .ok_or_else(|| actix_web::error::ErrorBadRequest("Template wajib diisi"))?;
+    let title_in = fields.title.unwrap_or_default();
+    let title = if title_in.trim().is_empty() {
+        let ts = humantime::format_rfc3339(SystemTime::now()).to_string();
+        format!("twibbon-{}-{}", ts.replace([':', 'T', 'Z', '.'], ""), &random_name(""))
+    } else {
+        sanitize(&title_in)
+    };
+
+    let tpl = templates()
+        .iter()
+        .find(|t| t.key == template_key && t.is_usable())
+        .ok_or_else(|| actix_web::error::ErrorBadRequest("Template tidak dikenali"))?
+        .clone();
+
+    let raw_paths: Vec<PathBuf> = fields.uploads.iter().map(|u| u.path.clone()).collect();
+    let need = tpl.video_count();
+    let what = if source == "photo" { "foto" } else { "video" };
+    let problem = if fields.uploads.is_empty() {
+        Some(format!("Upload {what} gagal"))
+    } else if fields.uploads.len() != need {
+        Some(format!("Template ini butuh {need} {what} (diterima {})", fields.uploads.len()))
     } else {
-        // Upload biasa: wajib MP4
-        if ext.as_str() != "mp4" || !(mime.contains("video/mp4") || mime == "application/octet-stream") {
-            let _ = fs::remove_file(&upload_path).await;
-            return Err(actix_web::error::ErrorBadRequest("File harus MP4."));
-        }
-        let input_path = PathBuf::from(format!("uploads/{}.mp4", random_name("vid_")));
-        fs::rename(&upload_path, &input_path)
-            .await
-            .map_err(actix_web::error::ErrorInternalServerError)?;
-        input_path
+        tpl.text_fields()
+            .into_iter()
+            .find(|tb| tb.required && !fields.texts.contains_key(&tb.field))
+            .map(|tb| format!("Kolom \"{}\" wajib diisi", tb.label()))
     };
+    if let Some(msg) = problem {
+        remove_files(&raw_paths).await;
+        return Err(actix_web::error::ErrorBadRequest(msg));
+    }
+
+    // --- Normalisasi/Validasi ke MP4 (satu input_path per video) ---
+    let mut inputs: Vec<PathBuf> = Vec::new();
+    for (i, upload) in fields.uploads.into_iter().enumerate() {
+        match normalize_upload(&source, upload, &tpl, i).await {
+            Ok(path) => inputs.push(path),
+            Err(e) => {
+                remove_files(&inputs).await;
+                remove_files(&raw_paths[i + 1..]).await;
+                return Err(e);
+            }
+        }
+    }
+    if source == "photo" {
+        return process_photo(&tpl, inputs, &fields.texts, &title).await;
+    }
 
     // ===== Proses FFmpeg utama =====
-    let dur = ffprobe_duration(&BINS.ffprobe, &input_path)
-        .await
-        .map_err(actix_web::error::ErrorBadRequest)?;
-    if dur <= 0.0 {
-        let _ = fs::remove_file(&input_path).await;
-        return Err(actix_web::error::ErrorBadRequest(
-            "Tidak bisa membaca durasi video (ffprobe).",
-        ));
+    let mut clips = Vec::new();
+    for path in &inputs {
+        let d = match ffprobe_duration(&BINS.ffprobe, path).await {
+            Ok(d) => d,
+            Err(e) => {
+                remove_files(&inputs).await;
+                return Err(actix_web::error::ErrorBadRequest(e));
+            }
+        };
+        if d <= 0.0 {
+            remove_files(&inputs).await;
+            return Err(actix_web::error::ErrorBadRequest(
+                "Tidak bisa membaca durasi video (ffprobe).",
+            ));
+        }
+        clips.push(d);
     }
+    // kolase mengikuti klip terpanjang
+    let dur = clips.iter().copied().fold(0.0, f64::max);
     let need_trim = ALLOW_TRIM && dur > (MAX_DURATION_SEC + 0.3);
+    let out_dur = if need_trim { MAX_DURATION_SEC } else { dur };
+    let audio_only = tpl.visualizer.is_some()
+        && inputs
+            .first()
+            .is_some_and(|p| AUDIO_UPLOAD_EXTS.contains(&lower_ext(&p.to_string_lossy()).as_str()));
+    // bumper tidak dihitung ke MAX_DURATION_SEC; durasinya sudah di-probe saat dimuat
+    let [intro, outro] = tpl.bumpers.as_ref().map_or([None, None], |b| b.probed);
+    let mut audio_inputs = Vec::new();
+    if tpl.collage.is_some()
+        || tpl.bumpers.is_some()
+        || tpl.music.as_ref().is_some_and(Music::active)
+    {
+        for (i, path) in inputs.iter().enumerate() {
+            if ffprobe_has_audio(&BINS.ffprobe, path).await {
+                audio_inputs.push(i);
+            }
+        }
+    }
+
+    let mut ctx = GraphCtx {
+        windows: tpl.overlays.iter().map(|ol| ol.window(out_dur)).collect(),
+        duration: out_dur,
+        clips,
+        audio_inputs,
+        intro,
+        outro,
+        audio_only,
+        ..Default::default()
+    };
+    if let Err(e) = render_texts(&tpl, &fields.texts, &mut ctx).await {
+        remove_files(&inputs).await;
+        return Err(e);
+    }
 
     // siapkan filter graph
-    let (graph, last_index) = build_filter_graph(&tpl);
+    let graph = build_filter_graph(&tpl, &ctx);
     let fc_file = PathBuf::from(format!("uploads/fc_{}.txt", random_name("")));
-    fs::write(&fc_file, &graph)
-        .await
-        .map_err(actix_web::error::ErrorInternalServerError)?;
 
     // build command (sekali saja)
-    let out_file_name = format!(
-        "{}.mp4",
-        sanitize(&title)
-            .chars()
-            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
-            .collect::<String>()
-    );
-    let out_file_rel = format!("outputs/{}", out_file_name);
+    let out_file_rel = format!("outputs/{}", output_file_name(&title, "mp4"));
     let out_file = PathBuf::from(&out_file_rel);
 
-    let mut cmd = Command::new(&BINS.ffmpeg);
-    cmd.arg("-y").arg("-i").arg(&input_path);
-    for ol in &tpl.overlays {
-        cmd.args(["-loop", "1", "-framerate", "30"]).arg("-i").arg(ol.path);
-    }
-    if need_trim {
-        cmd.arg("-t").arg(format!("{}", MAX_DURATION_SEC));
-    }
-    cmd.arg("-filter_complex_script")
-        .arg(&fc_file)
-        .args(["-c:v", "libx264"])
-        .args(["-crf", &CRF.to_string()])
-        .args(["-preset", PRESET])
-        .args(["-pix_fmt", "yuv420p"])
-        .args(["-c:a", "aac"])
-        .args(["-movflags", "+faststart"])
-        .arg("-shortest")
-        .args(["-map", &format!("[v{}]", last_index)])
-        .args(["-map", "0:a?"])
-        .arg(&out_file)
-        .stderr(Stdio::piped())
-        .stdout(Stdio::piped());
-
-    let full_cmd_for_view = {
-        let mut s = format!("{} -y -i \"{}\" ", &BINS.ffmpeg, input_path.display());
-        for ol in &tpl.overlays {
-            let _ = ol;
-            s.push_str("-loop 1 -framerate 30 -i ");
-            s.push('"');
-            // safe to print raw path here; it's a static string
-            s.push_str(ol.path);
-            s.push('"');
-            s.push(' ');
-        }
-        if need_trim {
-            s.push_str(&format!("-t {} ", MAX_DURATION_SEC));
-        }
-        s.push_str(&format!(
-            "-filter_complex_script \"{}\" -c:v libx264 -crf {} -preset {} -pix_fmt yuv420p -c:a aac -movflags +faststart -shortest -map [v{}] -map 0:a? \"{}\"",
-            fc_file.display(), CRF, PRESET, last_index, out_file.display()
-        ));
-        s
-    };
+    let fps = tpl.output.fps.to_string();
+    let mut args: Vec<String> = vec!["-y".into()];
+    args.extend(visual_input_args(&tpl, &inputs));
+    if let Some(music) = tpl.music.as_ref().filter(|m| m.active()) {
+        args.extend(to_args(&["-stream_loop", "-1", "-i"]));
+        args.push(path_arg(&music.path));
+    }
+    for clip in tpl.bumpers.iter().flat_map(Bumpers::clips) {
+        args.extend(["-i".into(), path_arg(clip)]);
+    }
+    // dengan bumper, bagian peserta sudah dipotong di dalam graph
+    if need_trim && tpl.bumpers.is_none() {
+        args.extend(["-t".into(), format!("{}", MAX_DURATION_SEC)]);
+    }
+    args.extend(["-filter_complex_script".into(), path_arg(&fc_file)]);
+    args.extend(
+        [
+            "-c:v", "libx264", "-crf", &CRF.to_string(), "-preset", PRESET,
+            "-pix_fmt", "yuv420p", "-r", &fps, "-c:a", "aac", "-movflags", "+faststart",
+            "-shortest", "-map", &format!("[{}]", graph.video),
+        ]
+        .map(String::from),
+    );
+    if let Some(audio) = &graph.audio {
+        args.extend(["-map".into(), audio.clone()]);
+    }
+    args.push(path_arg(&out_file));
 
-    let out = cmd
-        .output()
-        .await
-        .map_err(actix_web::error::ErrorInternalServerError)?;
-    let _ = fs::remove_file(&fc_file).await;
-    let _ = fs::remove_file(&input_path).await;
+    let full_cmd_for_view = format_cmd(&BINS.ffmpeg, &args);
+    let out = run_ffmpeg(&fc_file, &graph.script, &args).await;
+    cleanup_render(&inputs, &fc_file, &ctx).await;
+    let out = out?;
 
     if !out.status.success()
         || !out_file.exists()
@@ -740,7 +3814,68 @@ async fn process_upload(payload: Multipart) -> Result<impl Responder, ActixError
         let debug_html = format!(
             "<h3>Gagal generate video</h3><pre>{}</pre><pre>--- filter graph ---\n{}</pre><pre>{}</pre>",
             html_escape(&full_cmd_for_view),
-            html_escape(&graph),
+            html_escape(&graph.script),
+            html_escape(&errlog),
+        );
+        return Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(debug_html));
+    }
+
+    let page =
+        render_result_page(&title, &format!("/{}", out_file_rel), &full_cmd_for_view, false);
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(page))
+}
+
+// Mode foto: graph yang sama (crop/pad, slot, overlay, teks) dirender jadi satu frame.
+// Semua overlay tampil penuh di posisi keyframe terakhirnya; jendela waktu dan fade
+// tidak berlaku untuk foto.
+async fn process_photo(
+    tpl: &Template,
+    inputs: Vec<PathBuf>,
+    texts: &HashMap<String, String>,
+    title: &str,
+) -> Result<HttpResponse, ActixError> {
+    let full = Window { start: 0.0, end: 1.0, fade_in: 0.0, fade_out: 0.0 };
+    let mut ctx = GraphCtx {
+        windows: vec![Some(full); tpl.overlays.len()],
+        duration: 1.0,
+        still: true,
+        ..Default::default()
+    };
+    if let Err(e) = render_texts(tpl, texts, &mut ctx).await {
+        remove_files(&inputs).await;
+        return Err(e);
+    }
+    let graph = build_filter_graph(tpl, &ctx);
+    let fc_file = PathBuf::from(format!("uploads/fc_{}.txt", random_name("")));
+
+    let format = tpl.photo.format;
+    let out_file_rel = format!("outputs/{}", output_file_name(title, format.ext()));
+    let out_file = PathBuf::from(&out_file_rel);
+    let mut args: Vec<String> = vec!["-y".into()];
+    args.extend(visual_input_args(tpl, &inputs));
+    args.extend(["-filter_complex_script".into(), path_arg(&fc_file)]);
+    args.extend(["-map".into(), format!("[{}]", graph.video)]);
+    args.extend(to_args(&["-frames:v", "1", "-update", "1"]));
+    if format == PhotoFormat::Jpg {
+        args.extend(to_args(&["-q:v", "2"]));
+    }
+    args.push(path_arg(&out_file));
+
+    let full_cmd_for_view = format_cmd(&BINS.ffmpeg, &args);
+    let out = run_ffmpeg(&fc_file, &graph.script, &args).await;
+    cleanup_render(&inputs, &fc_file, &ctx).await;
+    let out = out?;
+
+    if !out.status.success() || !out_file.exists() {
+        let errlog = String::from_utf8_lossy(&out.stderr);
+        let debug_html = format!(
+            "<h3>Gagal generate foto</h3><pre>{}</pre><pre>--- filter graph ---\n{}</pre><pre>{}</pre>",
+            html_escape(&full_cmd_for_view),
+            html_escape(&graph.script),
             html_escape(&errlog),
         );
         return Ok(HttpResponse::Ok()
@@ -748,12 +3883,383 @@ async fn process_upload(payload: Multipart) -> Result<impl Responder, ActixError
             .body(debug_html));
     }
 
-    let page = render_result_page(&title, &format!("/{}", out_file_rel), &full_cmd_for_view);
+    let page = render_result_page(title, &format!("/{}", out_file_rel), &full_cmd_for_view, true);
     Ok(HttpResponse::Ok()
         .content_type("text/html; charset=utf-8")
         .body(page))
 }
 
+// ================== ADMIN API ==================
+// Aktif hanya jika VIBBON_ADMIN_TOKEN di-set. Kirim header
+// `Authorization: Bearer <token>`.
+const MAX_ASSET_BYTES: u64 = 50 * 1024 * 1024;
+const ASSET_EXTS: [&str; 14] = [
+    "png", "apng", "gif", "jpg", "jpeg", "webm", "mp4", "ttf", "otf", "cube", "mp3", "m4a",
+    "ogg", "wav",
+];
+
+static ADMIN_TOKEN: Lazy<Option<String>> = Lazy::new(|| {
+    env::var("VIBBON_ADMIN_TOKEN")
+        .ok()
+        .filter(|t| !t.trim().is_empty())
+});
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn check_admin(req: &HttpRequest) -> Result<(), ActixError> {
+    let Some(token) = ADMIN_TOKEN.as_deref() else {
+        return Err(actix_web::error::ErrorForbidden(
+            "Admin API nonaktif (set VIBBON_ADMIN_TOKEN).",
+        ));
+    };
+    let given = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or("");
+    if !constant_time_eq(given.trim().as_bytes(), token.as_bytes()) {
+        return Err(actix_web::error::ErrorUnauthorized("Token admin tidak valid"));
+    }
+    Ok(())
+}
+
+fn bad_request(e: anyhow::Error) -> ActixError {
+    actix_web::error::ErrorBadRequest(format!("{:#}", e))
+}
+
+#[derive(Default)]
+struct AdminFields {
+    key: Option<String>,
+    manifest: Option<(String, String)>, // (nama file manifest, isi)
+    assets: Vec<String>,
+}
+
+// Nama aset boleh memuat subfolder relatif (mis. `fonts/Inter-Bold.ttf`) supaya path
+// di manifest bisa dipakai apa adanya. Tiap bagian disanitasi; `.`/`..`, path absolut,
+// dan nama yang menimpa manifest ditolak.
+fn asset_rel_path(name: &str) -> Result<String, ActixError> {
+    let invalid = || actix_web::error::ErrorBadRequest(format!("Nama aset {} tidak valid", name));
+    let mut parts = Vec::new();
+    for part in name.split(['/', '\\']) {
+        if part.is_empty() || part == "." || part == ".." {
+            return Err(invalid());
+        }
+        let part = sanitize(part);
+        if part.is_empty() || part == "." || part == ".." {
+            return Err(invalid());
+        }
+        parts.push(part);
+    }
+    if parts.len() == 1 && MANIFEST_NAMES.iter().any(|m| m.eq_ignore_ascii_case(&parts[0])) {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "Nama aset {} bentrok dengan manifest",
+            name
+        )));
+    }
+    Ok(parts.join("/"))
+}
+
+// Simpan manifest + aset ke folder staging. Aset ditulis dengan nama file
+// aslinya (disanitasi, subfolder dipertahankan) sehingga bisa dirujuk langsung dari
+// manifest.
+async fn save_admin_multipart(
+    mut payload: Multipart,
+    staging: &Path,
+) -> Result<AdminFields, ActixError> {
+    let mut fields = AdminFields::default();
+
+    while let Some(item) = payload.next().await {
+        let mut field = item?;
+        let cd = field.content_disposition().clone();
+        let name = cd.get_name().unwrap_or("").to_string();
+        let filename = cd.get_filename().map(sanitize);
+
+        match name.as_str() {
+            "key" => {
+                let key = read_text_field(&mut field, MAX_FIELD_BYTES).await?;
+                fields.key = Some(key.trim().to_string());
+            }
+            "manifest" => {
+                let text = read_text_field(&mut field, MAX_MANIFEST_BYTES).await?;
+                let is_json = filename.as_deref().map(lower_ext).as_deref() == Some("json")
+                    || text.trim_start().starts_with('{');
+                let manifest_name = if is_json { MANIFEST_NAMES[1] } else { MANIFEST_NAMES[0] };
+                fields.manifest = Some((manifest_name.to_string(), text));
+            }
+            "asset" => {
+                let filename = cd
+                    .get_filename()
+                    .filter(|f| !f.is_empty())
+                    .ok_or_else(|| actix_web::error::ErrorBadRequest("Aset wajib punya nama file"))?;
+                let filename = asset_rel_path(filename)?;
+                if !ASSET_EXTS.contains(&lower_ext(&filename).as_str()) {
+                    return Err(actix_web::error::ErrorBadRequest(format!(
+                        "Aset {} tidak didukung (hanya {})",
+                        filename,
+                        ASSET_EXTS.join(", ")
+                    )));
+                }
+                let path = staging.join(&filename);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).await?;
+                }
+                let size = write_field(&mut field, &path, MAX_ASSET_BYTES).await?;
+                if size == 0 {
+                    return Err(actix_web::error::ErrorBadRequest(format!(
+                        "Ukuran aset {} tidak valid",
+                        filename
+                    )));
+                }
+                fields.assets.push(filename);
+            }
+            _ => {
+                // kosongkan field yang tidak dikenal
+                read_text_field(&mut field, MAX_FIELD_BYTES).await?;
+            }
+        }
+    }
+
+    Ok(fields)
+}
+
+fn check_asset_signature(path: &Path) -> anyhow::Result<()> {
+    let mut head = [0u8; 8];
+    let mut f = std::fs::File::open(path)?;
+    std::io::Read::read_exact(&mut f, &mut head).ok();
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let (ok, kind) = match lower_ext(&name).as_str() {
+        "png" | "apng" => (head == *b"\x89PNG\r\n\x1a\n", "PNG"),
+        "gif" => (head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a"), "GIF"),
+        // background/kanvas chroma; JPEG tidak punya alpha jadi tetap ditolak sebagai overlay
+        "jpg" | "jpeg" => (head.starts_with(&[0xff, 0xd8, 0xff]), "JPEG"),
+        "webm" => (head.starts_with(&[0x1a, 0x45, 0xdf, 0xa3]), "WebM"),
+        "ttf" | "otf" => (
+            head.starts_with(&[0, 1, 0, 0]) || head.starts_with(b"OTTO") || head.starts_with(b"true"),
+            "font TTF/OTF",
+        ),
+        // isi .cube dicek penuh oleh loader
+        "cube" => (head.is_ascii(), "LUT .cube (teks)"),
+        "mp3" => (
+            head.starts_with(b"ID3") || (head[0] == 0xff && head[1] & 0xe0 == 0xe0),
+            "MP3",
+        ),
+        "mp4" | "m4a" => (&head[4..8] == b"ftyp", "MP4/M4A"),
+        "ogg" => (head.starts_with(b"OggS"), "OGG"),
+        "wav" => (head.starts_with(b"RIFF"), "WAV"),
+        _ => (false, "aset yang didukung"),
+    };
+    if !ok {
+        anyhow::bail!("{} bukan file {}", name, kind);
+    }
+    Ok(())
+}
+
+// Tulis manifest (jika ada), lalu validasi seluruh folder staging.
+fn finalize_staging(staging: &Path, key: &str, fields: &AdminFields) -> anyhow::Result<Template> {
+    if let Some((name, text)) = &fields.manifest {
+        for n in MANIFEST_NAMES {
+            let _ = std::fs::remove_file(staging.join(n));
+        }
+        std::fs::write(staging.join(name), text)?;
+    }
+    for asset in &fields.assets {
+        check_asset_signature(&staging.join(asset))?;
+    }
+    let mut tpl = load_template_as(staging, key)?;
+    let problems = validate_template(&mut tpl);
+    if !problems.is_empty() {
+        anyhow::bail!("{}", problems.join("; "));
+    }
+    Ok(tpl)
+}
+
+// ffprobe + rename dijalankan di thread blocking agar worker actix tidak tertahan.
+// `create` = gagal bila key sudah dipakai template lain.
+async fn validate_and_publish(
+    staging: PathBuf,
+    key: String,
+    fields: AdminFields,
+    create: bool,
+) -> Result<Template, ActixError> {
+    let (st, k) = (staging.clone(), key.clone());
+    let tpl = web::block(move || finalize_staging(&st, &k, &fields))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .map_err(bad_request)?;
+    web::block(move || publish_staging(&staging, &key, create))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => {
+                actix_web::error::ErrorConflict("Template sudah ada")
+            }
+            _ => actix_web::error::ErrorInternalServerError(e),
+        })?;
+    Ok(tpl)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+fn template_dir(key: &str) -> PathBuf {
+    Path::new(TEMPLATES_DIR).join(key)
+}
+
+// Terbitkan staging sebagai revisi baru, pindahkan pointer `current`, lalu muat
+// ulang katalog. Revisi lama dipensiunkan saat reload dan baru dihapus setelah
+// render yang memakainya selesai. Cek `create` dilakukan di bawah lock yang sama
+// supaya dua POST dengan key sama tidak sama-sama lolos.
+fn publish_staging(staging: &Path, key: &str, create: bool) -> std::io::Result<()> {
+    let _guard = CATALOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if create && live_dir(key).is_some() {
+        return Err(std::io::ErrorKind::AlreadyExists.into());
+    }
+    let dir = template_dir(key);
+    let legacy = current_revision(&dir).is_none() && live_dir(key).is_some();
+    std::fs::create_dir_all(&dir)?;
+    let rev = new_revision_name();
+    std::fs::rename(staging, dir.join(&rev))?;
+    let tmp = dir.join(format!(".{}.tmp", CURRENT_REV));
+    std::fs::write(&tmp, &rev)?;
+    std::fs::rename(&tmp, dir.join(CURRENT_REV))?;
+    if legacy {
+        // manifest format lama tidak dibutuhkan render yang sedang jalan
+        for name in MANIFEST_NAMES {
+            let _ = std::fs::remove_file(dir.join(name));
+        }
+    }
+    reload_locked();
+    Ok(())
+}
+
+// Lepas template dari katalog; asetnya dihapus setelah render terakhir selesai.
+fn unpublish(key: &str) -> std::io::Result<()> {
+    let _guard = CATALOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = template_dir(key);
+    if current_revision(&dir).is_some() {
+        std::fs::remove_file(dir.join(CURRENT_REV))?;
+    } else {
+        for name in MANIFEST_NAMES {
+            let _ = std::fs::remove_file(dir.join(name));
+        }
+        AssetDir::retire(&dir, true);
+    }
+    reload_locked();
+    Ok(())
+}
+
+fn new_staging() -> Result<PathBuf, ActixError> {
+    ensure_dirs().map_err(actix_web::error::ErrorInternalServerError)?;
+    let staging = PathBuf::from(format!("uploads/{}", random_name("tpl_")));
+    std::fs::create_dir_all(&staging).map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(staging)
+}
+
+#[get("/admin/templates")]
+async fn admin_list(req: HttpRequest) -> Result<impl Responder, ActixError> {
+    check_admin(&req)?;
+    Ok(HttpResponse::Ok().json(&*templates()))
+}
+
+#[post("/admin/templates")]
+async fn admin_create(req: HttpRequest, payload: Multipart) -> Result<impl Responder, ActixError> {
+    check_admin(&req)?;
+    let staging = new_staging()?;
+    let result = async {
+        let fields = save_admin_multipart(payload, &staging).await?;
+        let key = fields
+            .key
+            .clone()
+            .filter(|k| valid_template_key(k))
+            .ok_or_else(|| {
+                actix_web::error::ErrorBadRequest("Key wajib diisi (huruf, angka, '-' dan '_')")
+            })?;
+        // cek awal supaya tidak perlu validasi; penentunya tetap cek di publish_staging
+        if live_dir(&key).is_some() {
+            return Err(actix_web::error::ErrorConflict("Template sudah ada"));
+        }
+        if fields.manifest.is_none() {
+            return Err(actix_web::error::ErrorBadRequest("Manifest wajib diisi"));
+        }
+        validate_and_publish(staging.clone(), key.clone(), fields, true).await
+    }
+    .await;
+
+    match result {
+        Ok(tpl) => Ok(HttpResponse::Created().json(reloaded(&tpl.key).unwrap_or(tpl))),
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging).await;
+            Err(e)
+        }
+    }
+}
+
+#[put("/admin/templates/{key}")]
+async fn admin_update(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: Multipart,
+) -> Result<impl Responder, ActixError> {
+    check_admin(&req)?;
+    let key = key.into_inner();
+    let Some(live) = valid_template_key(&key).then(|| live_dir(&key)).flatten() else {
+        return Err(actix_web::error::ErrorNotFound("Template tidak ditemukan"));
+    };
+    let staging = new_staging()?;
+    let result = async {
+        // aset lama bisa puluhan MB, jadi salinannya juga di thread blocking
+        let st = staging.clone();
+        web::block(move || copy_dir(&live, &st))
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        let fields = save_admin_multipart(payload, &staging).await?;
+        validate_and_publish(staging.clone(), key.clone(), fields, false).await
+    }
+    .await;
+
+    match result {
+        Ok(tpl) => Ok(HttpResponse::Ok().json(reloaded(&tpl.key).unwrap_or(tpl))),
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging).await;
+            Err(e)
+        }
+    }
+}
+
+#[delete("/admin/templates/{key}")]
+async fn admin_delete(req: HttpRequest, key: web::Path<String>) -> Result<impl Responder, ActixError> {
+    check_admin(&req)?;
+    let key = key.into_inner();
+    if !valid_template_key(&key) || live_dir(&key).is_none() {
+        return Err(actix_web::error::ErrorNotFound("Template tidak ditemukan"));
+    }
+    web::block(move || unpublish(&key))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Versi template dari katalog hasil reload (path overlay sudah menunjuk ke folder final).
+fn reloaded(key: &str) -> Option<Template> {
+    templates().iter().find(|t| t.key == key).cloned()
+}
+
 // ================== MAIN ==================
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -763,11 +4269,21 @@ async fn main() -> std::io::Result<()> {
         "FFmpeg: {}\nFFprobe: {}\nServing at: http://127.0.0.1:8080/",
         BINS.ffmpeg, BINS.ffprobe
     );
+    if !ffprobe_available() {
+        eprintln!("[templates] PERINGATAN: ffprobe tidak ditemukan; semua template ditolak");
+    }
+    // muat template sekarang supaya manifest/aset yang rusak langsung terlihat di log
+    Lazy::force(&TEMPLATES);
+    spawn_template_watcher();
 
     HttpServer::new(|| {
         App::new()
             .service(home)
             .service(process_upload)
+            .service(admin_list)
+            .service(admin_create)
+            .service(admin_update)
+            .service(admin_delete)
             .service(Files::new("/outputs", "outputs").show_files_listing())
             .service(Files::new("/templates", "templates").show_files_listing())
     })
@@ -775,3 +4291,445 @@ async fn main() -> std::io::Result<()> {
     .run()
     .await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visualizer_uses_background_with_crop_fill() {
+        let mut tpl: Template = toml::from_str(
+            r#"
+            title = "Podcast"
+            overlays = []
+
+            [output]
+            fill = "crop"
+
+            [background]
+            type = "image"
+            path = "bg.png"
+
+            [visualizer]
+            x = 60
+            y = 900
+            w = 600
+            h = 200
+            "#,
+        )
+        .unwrap();
+        tpl.output.normalize().unwrap();
+        let ctx = GraphCtx {
+            duration: 10.0,
+            clips: vec![10.0],
+            audio_only: true,
+            ..Default::default()
+        };
+
+        let bg_in = tpl.video_count();
+        let graph = build_filter_graph(&tpl, &ctx);
+        assert!(
+            graph.script.contains(&format!("[{bg_in}:v]")),
+            "background tidak dipakai: {}",
+            graph.script
+        );
+        let args = visual_input_args(&tpl, &[PathBuf::from("uploads/aud_test.mp3")]);
+        assert!(args.iter().any(|a| a.ends_with("bg.png")), "{args:?}");
+    }
+
+    #[test]
+    fn time_spec_parses_and_resolves() {
+        let parse = |s: &str| TimeSpec::try_from(TimeRaw::Text(s.into())).ok();
+        // (teks, hasil parse, detik pada video 20 detik)
+        let cases = [
+            ("end", Some(TimeSpec::FromEnd(0.0)), 20.0),
+            ("end-3", Some(TimeSpec::FromEnd(3.0)), 17.0),
+            (" end - 2.5 ", Some(TimeSpec::FromEnd(2.5)), 17.5),
+            ("end-30", Some(TimeSpec::FromEnd(30.0)), 0.0),
+            ("25%", Some(TimeSpec::Percent(25.0)), 5.0),
+            ("100%", Some(TimeSpec::Percent(100.0)), 20.0),
+            ("1.5", Some(TimeSpec::Secs(1.5)), 1.5),
+        ];
+        for (text, spec, secs) in cases {
+            let got = parse(text);
+            assert!(got == spec, "parse `{text}`");
+            assert_eq!(got.unwrap().resolve(20.0), secs, "resolve `{text}`");
+        }
+        for bad in ["-1", "end+3", "end-", "end--3", "-5%", "100.5%", "abc", "", "inf"] {
+            assert!(parse(bad).is_none(), "`{bad}` harus ditolak");
+        }
+        assert!(TimeSpec::try_from(TimeRaw::Secs(-0.5)).is_err());
+
+        // angka dan teks sama-sama diterima di manifest
+        let ol: Overlay = toml::from_str(
+            r#"
+            type = "full"
+            path = "frame.png"
+            start = 2
+            end = "end-3"
+            "#,
+        )
+        .unwrap();
+        assert!(ol.start == TimeSpec::Secs(2.0) && ol.end == TimeSpec::FromEnd(3.0));
+        assert!(toml::from_str::<Overlay>("type = \"full\"\nend = \"150%\"").is_err());
+    }
+
+    #[test]
+    fn overlay_window_clips_to_duration() {
+        let overlay = |start: &str, end: &str, fade_in: f64, fade_out: f64| -> Overlay {
+            toml::from_str(&format!(
+                r#"
+                type = "full"
+                path = "frame.png"
+                start = {start}
+                end = {end}
+                fade_in = {fade_in}
+                fade_out = {fade_out}
+                "#
+            ))
+            .unwrap()
+        };
+        let window = |ol: &Overlay, dur: f64| {
+            ol.window(dur).map(|w| (w.start, w.end, w.fade_in, w.fade_out))
+        };
+        // (overlay, durasi video, jendela yang diharapkan)
+        let cases = [
+            // jendela utuh di video panjang
+            (overlay("1", "\"end-2\"", 0.5, 0.5), 20.0, Some((1.0, 18.0, 0.5, 0.5))),
+            // end melewati durasi -> dipotong ke akhir video
+            (overlay("2", "10", 1.0, 1.0), 6.0, Some((2.0, 6.0, 1.0, 1.0))),
+            // jendela 1 detik: fade_in memakan semuanya, fade_out jadi 0
+            (overlay("5", "10", 1.5, 1.0), 6.0, Some((5.0, 6.0, 1.0, 0.0))),
+            // fade_out dipendekkan ke sisa jendela setelah fade_in
+            (overlay("\"50%\"", "\"end\"", 1.0, 2.0), 4.0, Some((2.0, 4.0, 1.0, 1.0))),
+            // start di luar video pendek -> tidak tampil
+            (overlay("8", "12", 0.0, 0.0), 6.0, None),
+            // "end-3" di video 2 detik jatuh ke 0, jendela kosong
+            (overlay("0", "\"end-3\"", 0.0, 0.0), 2.0, None),
+        ];
+        for (i, (ol, dur, expected)) in cases.iter().enumerate() {
+            assert_eq!(window(ol, *dur), *expected, "kasus {i}");
+        }
+    }
+
+    #[test]
+    fn asset_rel_path_rejects_traversal() {
+        let cases = [
+            ("fonts/Inter.ttf", Some("fonts/Inter.ttf")),
+            ("fonts\\Inter.ttf", Some("fonts/Inter.ttf")),
+            ("logo.png", Some("logo.png")),
+            ("../x.png", None),
+            ("a//b.png", None),
+            ("fonts\\..\\x.ttf", None),
+            ("/etc/x.png", None),
+            ("fonts/./x.ttf", None),
+            ("TEMPLATE.TOML", None),
+            ("template.json", None),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(asset_rel_path(name).ok().as_deref(), expected, "aset `{name}`");
+        }
+    }
+
+    // Font monospace: setiap glyph selebar 0.602 em, jadi di ukuran 24 satu karakter
+    // ~14.9 px (termasuk margin 3%) dan kotak 300 px memuat 20 karakter.
+    const TEST_FONT: &[u8] =
+        include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fonts/DejaVuSansMono.ttf"));
+
+    #[test]
+    fn layout_text_is_deterministic() {
+        let m = FontMetrics::parse(TEST_FONT).unwrap();
+        let name = "Kukuh Tripamungkas Wicaksono";
+        let layout = |w: i32, max_lines: usize| {
+            let tb: TextBox = toml::from_str(&format!(
+                r#"
+                field = "nama"
+                x = 0
+                y = 0
+                w = {w}
+                h = 120
+                font = "mono"
+                size = 48
+                min_size = 24
+                max_lines = {max_lines}
+                "#
+            ))
+            .unwrap();
+            let l = layout_text(&m, &tb, name);
+            (l.size, l.lines)
+        };
+
+        // satu baris: tidak muat bahkan di min_size, jadi dipotong dengan elipsis
+        assert_eq!(layout(300, 1), (24, vec!["Kukuh Tripamungkas…".to_string()]));
+        // dua baris: ukuran terbesar di mana "Kukuh Tripamungkas" (18 karakter) muat
+        assert_eq!(
+            layout(300, 2),
+            (26, vec!["Kukuh Tripamungkas".to_string(), "Wicaksono".to_string()])
+        );
+        // kotak lebih sempit: sisa kata digabung ke baris terakhir lalu diberi elipsis
+        assert_eq!(
+            layout(200, 2),
+            (24, vec!["Kukuh".to_string(), "Tripamungkas…".to_string()])
+        );
+
+        // kata yang lebih lebar dari kotak dipotong per karakter
+        assert_eq!(
+            wrap_words(&m, "Tripamungkas", 24, 0, 100.0),
+            vec!["Tripam".to_string(), "ungkas".to_string()]
+        );
+        assert_eq!(ellipsize(&m, "Kukuh", 24, 0, 300.0), "Kukuh");
+    }
+
+    #[test]
+    fn scale_keyframes_pad_to_fixed_box() {
+        let mut tpl: Template = toml::from_str(
+            r#"
+            title = "Logo"
+
+            [[overlays]]
+            type = "logo"
+            path = "logo.png"
+            w = 200
+            keyframes = [
+              { t = 0.0, scale = 0.5 },
+              { t = 1.0, scale = 1.5 },
+            ]
+            "#,
+        )
+        .unwrap();
+        tpl.output.normalize().unwrap();
+        tpl.overlays[0].probed = Some((400, 100));
+        let ctx = GraphCtx {
+            windows: vec![Some(Window { start: 0.0, end: 5.0, fade_in: 0.0, fade_out: 0.0 })],
+            duration: 5.0,
+            clips: vec![5.0],
+            ..Default::default()
+        };
+
+        // 200x50 dasar, skala terbesar 1.5 -> kotak 300x75 dibulatkan genap + sisa
+        let graph = build_filter_graph(&tpl, &ctx);
+        assert!(
+            graph.script.contains("eval=frame:flags=fast_bilinear,format=rgba,pad=w=302:h=78:"),
+            "{}",
+            graph.script
+        );
+    }
+
+    // Evaluator kecil untuk subset ekspresi FFmpeg yang dihasilkan graph (angka, variabel,
+    // + - * /, dan fungsi yang dipakai keyframe).
+    fn eval_expr(expr: &str, vars: &HashMap<&str, f64>) -> f64 {
+        fn sum(s: &mut &str, vars: &HashMap<&str, f64>) -> f64 {
+            let mut v = product(s, vars);
+            while let Some(op) = s.strip_prefix(['+', '-']).map(|_| s.as_bytes()[0]) {
+                *s = &s[1..];
+                let r = product(s, vars);
+                v = if op == b'+' { v + r } else { v - r };
+            }
+            v
+        }
+        fn product(s: &mut &str, vars: &HashMap<&str, f64>) -> f64 {
+            let mut v = atom(s, vars);
+            while let Some(op) = s.strip_prefix(['*', '/']).map(|_| s.as_bytes()[0]) {
+                *s = &s[1..];
+                let r = atom(s, vars);
+                v = if op == b'*' { v * r } else { v / r };
+            }
+            v
+        }
+        fn atom(s: &mut &str, vars: &HashMap<&str, f64>) -> f64 {
+            if let Some(rest) = s.strip_prefix('-') {
+                *s = rest;
+                return -atom(s, vars);
+            }
+            if let Some(rest) = s.strip_prefix('(') {
+                *s = rest;
+                let v = sum(s, vars);
+                *s = &s[1..];
+                return v;
+            }
+            let end = s.find(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '.');
+            let (tok, rest) = s.split_at(end.unwrap_or(s.len()));
+            *s = rest;
+            if let Ok(n) = tok.parse() {
+                return n;
+            }
+            let Some(rest) = s.strip_prefix('(') else {
+                return vars[tok];
+            };
+            *s = rest;
+            let mut args = vec![sum(s, vars)];
+            while let Some(rest) = s.strip_prefix(',') {
+                *s = rest;
+                args.push(sum(s, vars));
+            }
+            *s = &s[1..];
+            match (tok, args.as_slice()) {
+                ("max", [a, b]) => a.max(*b),
+                ("trunc", [a]) => a.trunc(),
+                ("round", [a]) => a.round(),
+                ("pow", [a, b]) => a.powf(*b),
+                ("clip", [a, lo, hi]) => a.clamp(*lo, *hi),
+                ("lt", [a, b]) => (a < b) as i32 as f64,
+                ("if", [c, a, b]) => if *c != 0.0 { *a } else { *b },
+                _ => panic!("fungsi tak dikenal: {tok}"),
+            }
+        }
+        let expr = expr.replace("\\,", ",");
+        let mut s = expr.as_str();
+        let v = sum(&mut s, vars);
+        assert!(s.is_empty(), "sisa ekspresi: {s}");
+        v
+    }
+
+    // Posisi kiri atas gambar (bukan kotak pad) tiap overlay logo setelah gerak selesai.
+    fn settled_positions(tpl: &Template, still: bool) -> Vec<(f64, f64)> {
+        let n = tpl.overlays.len();
+        let ctx = GraphCtx {
+            windows: vec![Some(Window { start: 0.0, end: 5.0, fade_in: 0.0, fade_out: 0.0 }); n],
+            duration: 5.0,
+            clips: vec![5.0],
+            still,
+            ..Default::default()
+        };
+        let graph = build_filter_graph(tpl, &ctx);
+        let chains: Vec<&str> = graph.script.split(';').collect();
+        let opt = |opts: &str, key: &str| -> Option<String> {
+            opts.split(':').find_map(|o| o.strip_prefix(key)).map(str::to_string)
+        };
+        tpl.overlays
+            .iter()
+            .enumerate()
+            .map(|(i, ol)| {
+                let tag = format!("[ov{}]", i + 1);
+                let prep = chains.iter().find(|c| c.ends_with(&tag)).unwrap();
+                let overlay = format!("{tag}overlay=");
+                let place = chains.iter().find(|c| c.contains(&overlay)).unwrap();
+                let OverlayType::Logo { w: Some(base), .. } = ol.typ else { unreachable!() };
+                let (pw, ph) = ol.probed.unwrap();
+                let s = ol.keyframes.iter().filter_map(|k| k.scale).next_back().unwrap();
+                let img_w = ((base as f64 * s / 2.0).trunc() * 2.0).max(2.0);
+                let img_h = (img_w * ph as f64 / pw as f64).round();
+                // video: overlay menerima kotak pad dengan gambar di tengahnya
+                let (w, h) = match prep.split_once("pad=") {
+                    Some((_, pad)) => {
+                        let side = |k| opt(pad, k).unwrap().parse::<f64>().unwrap();
+                        (side("w="), side("h="))
+                    }
+                    None => (img_w, img_h),
+                };
+                let vars = HashMap::from([
+                    ("t", 5.0),
+                    ("main_w", tpl.output.w() as f64),
+                    ("main_h", tpl.output.h() as f64),
+                    ("w", w),
+                    ("h", h),
+                ]);
+                let (dx, dy) = (((w - img_w) / 2.0).trunc(), ((h - img_h) / 2.0).trunc());
+                let x = eval_expr(&opt(place, "x=").unwrap(), &vars) + dx;
+                let y = eval_expr(&opt(place, "y=").unwrap(), &vars) + dy;
+                (x, y)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn scale_keyframes_settle_like_photo() {
+        let mut tpl: Template = toml::from_str(
+            r#"
+            title = "Logo"
+
+            [[overlays]]
+            type = "logo"
+            path = "logo.png"
+            w = 200
+            keyframes = [
+              { t = 0.0, scale = 0.5 },
+              { t = 1.0, scale = 1.5 },
+            ]
+
+            [[overlays]]
+            type = "logo"
+            path = "badge.png"
+            w = 200
+            x = "(main_w-w)/2"
+            y = "main_h-h-40"
+            keyframes = [
+              { t = 0.0, scale = 1.5 },
+              { t = 1.0, scale = 0.75, ease = "in_out" },
+            ]
+            "#,
+        )
+        .unwrap();
+        tpl.output.normalize().unwrap();
+        tpl.overlays[0].probed = Some((400, 100));
+        tpl.overlays[1].probed = Some((300, 300));
+
+        let photo = settled_positions(&tpl, true);
+        let video = settled_positions(&tpl, false);
+        assert_eq!(photo, video);
+        // logo 300x75 tetap berjarak 24 px dari tepi kanan
+        assert_eq!(photo[0], (tpl.output.w() as f64 - 300.0 - 24.0, 24.0));
+    }
+
+    // PNG berpalet minimal: hanya struktur chunk yang dibaca, isi/CRC tidak dicek.
+    fn palette_png(chunks: &[(&[u8; 4], usize)]) -> Vec<u8> {
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (kind, len) in chunks {
+            png.extend((*len as u32).to_be_bytes());
+            png.extend(*kind);
+            png.extend(vec![0u8; len + 4]);
+        }
+        png
+    }
+
+    #[test]
+    fn palette_alpha_needs_trns() {
+        let dir = env::temp_dir();
+        let opaque = dir.join(format!("{}.png", random_name("pal_")));
+        let keyed = dir.join(format!("{}.png", random_name("pal_")));
+        std::fs::write(
+            &opaque,
+            palette_png(&[(b"IHDR", 13), (b"PLTE", 6), (b"IDAT", 10), (b"IEND", 0)]),
+        )
+        .unwrap();
+        std::fs::write(
+            &keyed,
+            palette_png(&[(b"IHDR", 13), (b"PLTE", 6), (b"tRNS", 1), (b"IDAT", 10), (b"IEND", 0)]),
+        )
+        .unwrap();
+
+        let (a, b) = (palette_has_alpha(&opaque), palette_has_alpha(&keyed));
+        let _ = std::fs::remove_file(&opaque);
+        let _ = std::fs::remove_file(&keyed);
+        assert!(!a, "PNG berpalet tanpa tRNS harus dianggap opak");
+        assert!(b, "PNG berpalet dengan tRNS punya alpha");
+    }
+
+    // GIF 1x1 dua frame: flag transparan hanya dinyalakan di GCE frame kedua.
+    fn two_frame_gif(second_transparent: bool) -> Vec<u8> {
+        let mut gif = b"GIF89a\x01\x00\x01\x00\x80\x00\x00".to_vec();
+        gif.extend([0u8; 6]); // global color table 2 warna
+        gif.extend(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00");
+        for transparent in [false, second_transparent] {
+            gif.extend([0x21, 0xF9, 0x04, transparent as u8, 0, 0, 0, 0]);
+            gif.extend(b"\x2C\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02\x44\x01\x00");
+        }
+        gif.push(0x3B);
+        gif
+    }
+
+    #[test]
+    fn gif_transparency_and_other_palettes() {
+        assert!(gif_has_transparency(&two_frame_gif(true)));
+        assert!(!gif_has_transparency(&two_frame_gif(false)));
+        // GIF terpotong tidak dianggap transparan
+        assert!(!gif_has_transparency(&two_frame_gif(true)[..40]));
+
+        // BMP 8-bit yang diberi nama .png: pal8 tapi tidak punya alpha
+        let bmp = env::temp_dir().join(format!("{}.png", random_name("pal_")));
+        std::fs::write(&bmp, b"BM\x00\x00\x00\x00\x00\x00\x00\x00\x36\x04\x00\x00").unwrap();
+        let alpha = palette_has_alpha(&bmp);
+        let _ = std::fs::remove_file(&bmp);
+        assert!(!alpha, "pal8 selain PNG/GIF harus dianggap opak");
+    }
+}